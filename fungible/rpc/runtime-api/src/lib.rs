@@ -0,0 +1,19 @@
+//! Runtime API definition for the Fungible pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+	pub trait FungibleApi<AccountId, TokenId, TokenBalance> where
+		AccountId: Codec,
+		TokenId: Codec,
+		TokenBalance: Codec,
+	{
+		/// The balance of `who` in `token_id`.
+		fn balance_of(token_id: TokenId, who: AccountId) -> TokenBalance;
+
+		/// The total supply of `token_id`.
+		fn total_supply(token_id: TokenId) -> TokenBalance;
+	}
+}
@@ -0,0 +1,58 @@
+//! Fungible pallet benchmarking.
+
+use super::*;
+
+use system::RawOrigin;
+use frame_benchmarking::{benchmarks, account};
+
+const SEED: u32 = 0;
+const MAX_USER_INDEX: u32 = 1000;
+
+benchmarks! {
+    _ {
+        let u in 1 .. MAX_USER_INDEX => ();
+    }
+
+    debug_create_token {
+        let u in ...;
+        let caller = account("caller", u, SEED);
+    }: _(RawOrigin::Signed(caller), 1_000_000u32.into())
+
+    transfer {
+        let u in ...;
+        let caller: T::AccountId = account("caller", u, SEED);
+        let id = Module::<T>::create_token(caller.clone(), 1_000_000u32.into());
+        let recipient: T::AccountId = account("recipient", u, SEED);
+        let recipient_lookup = T::Lookup::unlookup(recipient);
+    }: _(RawOrigin::Signed(caller), id, recipient_lookup, 1_000u32.into())
+
+    transfer_from {
+        let u in ...;
+        let owner: T::AccountId = account("owner", u, SEED);
+        let spender: T::AccountId = account("spender", u, SEED);
+        let id = Module::<T>::create_token(owner.clone(), 1_000_000u32.into());
+        <Allowance<T>>::insert((id, owner.clone(), spender.clone()), T::TokenBalance::from(1_000u32));
+        let owner_lookup = T::Lookup::unlookup(owner);
+        let recipient_lookup = T::Lookup::unlookup(spender.clone());
+    }: _(RawOrigin::Signed(spender), id, owner_lookup, recipient_lookup, 1_000u32.into())
+
+    approve {
+        let u in ...;
+        let caller: T::AccountId = account("caller", u, SEED);
+        let id = Module::<T>::create_token(caller.clone(), 1_000_000u32.into());
+        let spender: T::AccountId = account("spender", u, SEED);
+        let spender_lookup = T::Lookup::unlookup(spender);
+    }: _(RawOrigin::Signed(caller), id, spender_lookup, 1_000u32.into())
+
+    debug_mint {
+        let u in ...;
+        let caller: T::AccountId = account("caller", u, SEED);
+        let id = Module::<T>::create_token(caller.clone(), 1_000_000u32.into());
+    }: _(RawOrigin::Signed(caller.clone()), id, caller, 1_000u32.into())
+
+    debug_burn {
+        let u in ...;
+        let caller: T::AccountId = account("caller", u, SEED);
+        let id = Module::<T>::create_token(caller.clone(), 1_000_000u32.into());
+    }: _(RawOrigin::Signed(caller.clone()), id, caller, 1_000u32.into())
+}
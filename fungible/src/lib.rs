@@ -2,15 +2,21 @@
 
 use sp_runtime::traits::{
     Member, One, Zero, AtLeast32Bit, MaybeSerializeDeserialize, CheckedAdd,
-    StaticLookup,
+    StaticLookup, Saturating,
 };
 
 use frame_support::{
     decl_module, decl_storage, decl_event, decl_error, dispatch, ensure,
-    Parameter,
+    Parameter, weights::SimpleDispatchInfo,
 };
 use system::ensure_signed;
 
+mod weights;
+pub use weights::WeightInfo;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
 #[cfg(test)]
 mod mock;
 
@@ -26,6 +32,9 @@ pub trait Trait: system::Trait {
     
     type TokenId: Parameter + Member + AtLeast32Bit + Default + Copy
         + MaybeSerializeDeserialize;
+
+    /// Weight information for the dispatchables of this pallet.
+    type WeightInfo: WeightInfo;
 }
 
 decl_event!(
@@ -54,6 +63,8 @@ decl_error! {
         InsufficientFunds,
         /// Insufficient allowance to spend on behalf of an account.
         InsufficientAllowance,
+        /// A balance or the total supply would overflow.
+        Overflow,
     }
 }
 
@@ -67,6 +78,20 @@ decl_storage!(
         Balances get(balance_of): map hasher(opaque_blake2_256) (T::TokenId, T::AccountId) => T::TokenBalance;
         TotalSupply get(total_supply): map hasher(opaque_blake2_256) T::TokenId => T::TokenBalance;
     }
+    add_extra_genesis {
+        // Initial balances as (id, owner, amount). The token count is raised
+        // past the highest id listed.
+        config(balances): Vec<(T::TokenId, T::AccountId, T::TokenBalance)>;
+        build(|config: &GenesisConfig<T>| {
+            for (id, who, amount) in &config.balances {
+                Module::<T>::mint(*id, who.clone(), *amount)
+                    .expect("genesis balances do not overflow");
+                if *id >= Module::<T>::token_count() {
+                    <TokenCount<T>>::put(*id + One::one());
+                }
+            }
+        });
+    }
 );
 
 decl_module!(
@@ -76,6 +101,7 @@ decl_module!(
 
         fn deposit_event() = default;
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::debug_create_token())]
         pub fn debug_create_token(
             origin,
             #[compact] total_supply: T::TokenBalance,
@@ -88,6 +114,7 @@ decl_module!(
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::transfer())]
         pub fn transfer(
             origin,
             id: T::TokenId,
@@ -103,6 +130,7 @@ decl_module!(
             Self::do_transfer(id, sender.clone(), recipient.clone(), amount)
         }
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::transfer_from())]
         pub fn transfer_from(
             origin,
             id: T::TokenId,
@@ -128,6 +156,7 @@ decl_module!(
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::approve())]
         pub fn approve(
             origin,
             id: T::TokenId,
@@ -141,7 +170,7 @@ decl_module!(
             ensure!(!amount.is_zero(), Error::<T>::TransferZeroAmount);
 
             <Allowance<T>>::mutate((id, sender.clone(), a_spender.clone()), |allowed| {
-                *allowed += amount.clone();
+                *allowed = allowed.saturating_add(amount.clone());
             });
 
             Self::deposit_event(RawEvent::Approval(sender.clone(), a_spender.clone(), amount));
@@ -149,6 +178,7 @@ decl_module!(
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::debug_mint())]
         pub fn debug_mint(
             origin,
             id: T::TokenId,
@@ -160,6 +190,7 @@ decl_module!(
             Self::mint(id, to, amount)
         }
 
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::debug_burn())]
         pub fn debug_burn(origin, id: T::TokenId, from: T::AccountId, amount: T::TokenBalance) 
             -> dispatch::DispatchResult
         {
@@ -170,16 +201,22 @@ decl_module!(
 );
 
 impl<T: Trait> Module<T> {
+    /// Whether a token has been created or minted, at genesis or since.
+    /// Token ids below `token_count` need not all exist.
+    pub fn exists(id: T::TokenId) -> bool {
+        <TotalSupply<T>>::contains_key(id)
+    }
+
     pub fn mint(id: T::TokenId, to: T::AccountId, amount: T::TokenBalance)
         -> dispatch::DispatchResult
     {
-        <Balances<T>>::mutate((id, to), |bal| {
-            *bal += amount.clone();
-        });
+        let new_supply = Self::total_supply(id).checked_add(&amount)
+            .ok_or(Error::<T>::Overflow)?;
+        let new_balance = Self::balance_of((id, to.clone())).checked_add(&amount)
+            .ok_or(Error::<T>::Overflow)?;
 
-        <TotalSupply<T>>::mutate(id, |sup| {
-            *sup += amount;
-        });
+        <Balances<T>>::insert((id, to), new_balance);
+        <TotalSupply<T>>::insert(id, new_supply);
 
         Ok(())
     }
@@ -187,12 +224,13 @@ impl<T: Trait> Module<T> {
     pub fn burn(id: T::TokenId, from: T::AccountId, amount: T::TokenBalance)
         -> dispatch::DispatchResult
     {
-        <Balances<T>>::mutate((id, from), |bal| {
-            *bal -= amount.clone();
-        });
+        let balance = Self::balance_of((id, from.clone()));
+        ensure!(balance >= amount.clone(), Error::<T>::InsufficientFunds);
+
+        <Balances<T>>::insert((id, from), balance - amount.clone());
 
         <TotalSupply<T>>::mutate(id, |sup| {
-            *sup -= amount;
+            *sup = sup.saturating_sub(amount);
         });
 
         Ok(())
@@ -224,12 +262,13 @@ impl<T: Trait> Module<T> {
             Error::<T>::InsufficientFunds,
         );
 
-        <Balances<T>>::mutate((id, from.clone()), |balance| {
-            *balance -= amount.clone();
-        });
-        <Balances<T>>::mutate((id, to.clone()), |balance| {
-            *balance += amount.clone();
-        });
+        if from != to {
+            let to_balance = Self::balance_of((id, to.clone())).checked_add(&amount)
+                .ok_or(Error::<T>::Overflow)?;
+
+            <Balances<T>>::insert((id, from.clone()), from_balance - amount.clone());
+            <Balances<T>>::insert((id, to.clone()), to_balance);
+        }
 
         Self::deposit_event(RawEvent::Transfer(from.clone(), to.clone(), amount.clone()));
 
@@ -51,6 +51,7 @@ impl Trait for Test {
     // type Currency = pallet_balances::Module<Test>;
     type TokenBalance = u64;
     type TokenId = u64;
+    type WeightInfo = ();
 }
 
 // impl pallet_balances::Trait for Test {
@@ -68,3 +69,9 @@ pub type FungiblePallet = Module<Test>;
 pub fn new_test_ext() -> sp_io::TestExternalities {
     system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
 }
+
+pub fn new_test_ext_with_balances(balances: Vec<(u64, u64, u64)>) -> sp_io::TestExternalities {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    crate::GenesisConfig::<Test> { balances }.assimilate_storage(&mut t).unwrap();
+    t.into()
+}
@@ -1,5 +1,5 @@
-use crate::{ mock::* };
-use frame_support::{ assert_ok };
+use crate::{ mock::*, Error };
+use frame_support::{ assert_ok, assert_noop };
 
 #[test]
 fn it_works() {
@@ -25,6 +25,24 @@ fn it_creates_a_token() {
     });
 }
 
+#[test]
+fn it_creates_tokens_at_genesis() {
+    new_test_ext_with_balances(vec![(0, 1, 42), (0, 2, 8), (2, 1, 100)]).execute_with(|| {
+        assert_eq!(FungiblePallet::token_count(), 3);
+        assert_eq!(FungiblePallet::balance_of((0, 1)), 42);
+        assert_eq!(FungiblePallet::balance_of((0, 2)), 8);
+        assert_eq!(FungiblePallet::total_supply(0), 50);
+        assert_eq!(FungiblePallet::total_supply(1), 0);
+        assert_eq!(FungiblePallet::total_supply(2), 100);
+        assert!(FungiblePallet::exists(0));
+        assert!(!FungiblePallet::exists(1));
+        assert!(FungiblePallet::exists(2));
+
+        // New tokens do not reuse the ids of genesis tokens.
+        assert_eq!(FungiblePallet::create_token(1, 42), 3u64.into());
+    });
+}
+
 #[test]
 fn it_transfers_a_token() {
     new_test_ext().execute_with(|| {
@@ -56,4 +74,28 @@ fn it_creates_allowance_and_transfers() {
         assert_eq!(FungiblePallet::balance_of((0, 1)), 32);
         assert_eq!(FungiblePallet::balance_of((0, 3)), 10);
     });
-}
\ No newline at end of file
+}
+#[test]
+fn it_cannot_burn_more_than_the_balance() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(FungiblePallet::create_token(1, 42), 0u64.into());
+        assert_noop!(
+            FungiblePallet::burn(0, 1, 43),
+            Error::<Test>::InsufficientFunds
+        );
+        assert_ok!(FungiblePallet::burn(0, 1, 42));
+        assert_eq!(FungiblePallet::balance_of((0, 1)), 0);
+        assert_eq!(FungiblePallet::total_supply(0), 0);
+    });
+}
+
+#[test]
+fn it_cannot_mint_past_the_maximum_supply() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(FungiblePallet::create_token(1, u64::max_value()), 0u64.into());
+        assert_noop!(
+            FungiblePallet::mint(0, 2, 1),
+            Error::<Test>::Overflow
+        );
+    });
+}
@@ -0,0 +1,39 @@
+//! Weights for the Fungible pallet.
+
+use frame_support::weights::Weight;
+
+/// Weight functions needed by the Fungible pallet.
+pub trait WeightInfo {
+    fn debug_create_token() -> Weight;
+    fn transfer() -> Weight;
+    fn transfer_from() -> Weight;
+    fn approve() -> Weight;
+    fn debug_mint() -> Weight;
+    fn debug_burn() -> Weight;
+}
+
+/// The weight of reading a storage item.
+const READ_WEIGHT: Weight = 100_000;
+/// The weight of writing a storage item.
+const WRITE_WEIGHT: Weight = 200_000;
+
+/// `execution` plus the weight of `reads` storage reads and `writes` storage
+/// writes.
+fn weight(execution: Weight, reads: Weight, writes: Weight) -> Weight {
+    execution
+        .saturating_add(READ_WEIGHT.saturating_mul(reads))
+        .saturating_add(WRITE_WEIGHT.saturating_mul(writes))
+}
+
+// Default weights laid out as the `runtime-benchmarks` suite reports them: the
+// execution time of each call plus the storage items it reads and writes.
+// Runtimes should replace them with the results of the suite for their own
+// configuration.
+impl WeightInfo for () {
+    fn debug_create_token() -> Weight { weight(150_000, 1, 3) }
+    fn transfer() -> Weight { weight(150_000, 2, 2) }
+    fn transfer_from() -> Weight { weight(200_000, 3, 3) }
+    fn approve() -> Weight { weight(100_000, 0, 1) }
+    fn debug_mint() -> Weight { weight(150_000, 2, 2) }
+    fn debug_burn() -> Weight { weight(150_000, 2, 2) }
+}
@@ -0,0 +1,57 @@
+//! Runtime API definition for the Swaps pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+use sp_runtime::{DispatchError, traits::NumberFor};
+
+pub use pallet_swaps::{Asset, PoolInfo, PRICE_PRECISION};
+
+sp_api::decl_runtime_apis! {
+	pub trait SwapsApi<AccountId, SwapId, TokenId, TokenBalance> where
+		AccountId: Codec,
+		SwapId: Codec,
+		TokenId: Codec,
+		TokenBalance: Codec,
+	{
+		/// Quotes the final output of selling exactly `amount_in` of `asset_in`
+		/// along `path`.
+		fn quote_exact_in(path: Vec<SwapId>, asset_in: Asset<TokenId>, amount_in: TokenBalance)
+			-> Result<TokenBalance, DispatchError>;
+
+		/// Quotes the amount of `asset_in` sold when buying exactly
+		/// `amount_out` of the final asset along `path`.
+		fn quote_exact_out(path: Vec<SwapId>, asset_in: Asset<TokenId>, amount_out: TokenBalance)
+			-> Result<TokenBalance, DispatchError>;
+
+		/// The base asset and token reserves of a swap.
+		fn get_reserves(swap_id: SwapId) -> Option<(TokenBalance, TokenBalance)>;
+
+		/// The base asset and tokens set aside for the protocol in a swap and
+		/// not yet claimed.
+		fn unclaimed_protocol_fees(swap_id: SwapId) -> Option<(TokenBalance, TokenBalance)>;
+
+		/// The swap trading `token_id` against the native currency, if any.
+		fn get_swap_by_token(token_id: TokenId) -> Option<SwapId>;
+
+		/// The swap trading `asset_a` against `asset_b`, in either order, if any.
+		fn get_swap_by_pair(asset_a: Asset<TokenId>, asset_b: Asset<TokenId>) -> Option<SwapId>;
+
+		/// The base asset and tokens that burning `shares` of a swap would
+		/// return.
+		fn lp_share_value(swap_id: SwapId, shares: TokenBalance)
+			-> Result<(TokenBalance, TokenBalance), DispatchError>;
+
+		/// A summary of a swap and its reserves.
+		fn get_pool(swap_id: SwapId) -> Option<PoolInfo<AccountId, TokenId, TokenBalance>>;
+
+		/// Summaries of every swap, in order of creation.
+		fn list_pools() -> Vec<(SwapId, PoolInfo<AccountId, TokenId, TokenBalance>)>;
+
+		/// The time-weighted average prices of a swap over at least the last
+		/// `window` blocks, as (base per token, tokens per base) scaled
+		/// by `PRICE_PRECISION`.
+		fn consult(swap_id: SwapId, window: NumberFor<Block>) -> Result<(u128, u128), DispatchError>;
+	}
+}
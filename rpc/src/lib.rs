@@ -0,0 +1,225 @@
+//! RPC interface for the Swaps and Fungible pallets.
+
+use std::sync::Arc;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_swaps_rpc_runtime_api::{Asset, PoolInfo, SwapsApi as SwapsRuntimeApi};
+pub use pallet_fungible_rpc_runtime_api::FungibleApi as FungibleRuntimeApi;
+
+#[rpc]
+pub trait SwapsApi<BlockHash, AccountId, SwapId, TokenId, TokenBalance> {
+	/// Quotes the final output of selling exactly `amount_in` of `asset_in`
+	/// along `path`.
+	#[rpc(name = "swaps_quote")]
+	fn quote(
+		&self,
+		path: Vec<SwapId>,
+		asset_in: Asset<TokenId>,
+		amount_in: TokenBalance,
+		at: Option<BlockHash>,
+	) -> Result<TokenBalance>;
+
+	/// Quotes the amount of `asset_in` sold when buying exactly `amount_out`
+	/// of the final asset along `path`.
+	#[rpc(name = "swaps_quoteExactOut")]
+	fn quote_exact_out(
+		&self,
+		path: Vec<SwapId>,
+		asset_in: Asset<TokenId>,
+		amount_out: TokenBalance,
+		at: Option<BlockHash>,
+	) -> Result<TokenBalance>;
+
+	/// A summary of a swap and its reserves.
+	#[rpc(name = "swaps_getPool")]
+	fn get_pool(
+		&self,
+		swap_id: SwapId,
+		at: Option<BlockHash>,
+	) -> Result<Option<PoolInfo<AccountId, TokenId, TokenBalance>>>;
+
+	/// Summaries of every swap, in order of creation.
+	#[rpc(name = "swaps_listPools")]
+	fn list_pools(
+		&self,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(SwapId, PoolInfo<AccountId, TokenId, TokenBalance>)>>;
+}
+
+#[rpc]
+pub trait FungibleApi<BlockHash, AccountId, TokenId, TokenBalance> {
+	/// The balance of `who` in `token_id`.
+	#[rpc(name = "fungible_balanceOf")]
+	fn balance_of(&self, token_id: TokenId, who: AccountId, at: Option<BlockHash>) -> Result<TokenBalance>;
+
+	/// The total supply of `token_id`.
+	#[rpc(name = "fungible_totalSupply")]
+	fn total_supply(&self, token_id: TokenId, at: Option<BlockHash>) -> Result<TokenBalance>;
+}
+
+/// Error codes returned by the RPC handlers.
+pub enum Error {
+	/// The runtime API call failed.
+	RuntimeError,
+	/// The runtime rejected the request, e.g. an invalid path.
+	QuoteError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::QuoteError => 2,
+		}
+	}
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+fn quote_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::QuoteError.into()),
+		message: "Unable to quote the route.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Implements the `SwapsApi` RPC trait for interacting with the swaps.
+pub struct Swaps<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Swaps<C, B> {
+	/// Creates a new instance of the Swaps RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Swaps { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId, SwapId, TokenId, TokenBalance>
+	SwapsApi<<Block as BlockT>::Hash, AccountId, SwapId, TokenId, TokenBalance>
+	for Swaps<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: SwapsRuntimeApi<Block, AccountId, SwapId, TokenId, TokenBalance>,
+	AccountId: Codec,
+	SwapId: Codec,
+	TokenId: Codec,
+	TokenBalance: Codec,
+{
+	fn quote(
+		&self,
+		path: Vec<SwapId>,
+		asset_in: Asset<TokenId>,
+		amount_in: TokenBalance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<TokenBalance> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.quote_exact_in(&at, path, asset_in, amount_in)
+			.map_err(|e| runtime_error("Unable to query the runtime.", e))?
+			.map_err(quote_error)
+	}
+
+	fn quote_exact_out(
+		&self,
+		path: Vec<SwapId>,
+		asset_in: Asset<TokenId>,
+		amount_out: TokenBalance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<TokenBalance> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.quote_exact_out(&at, path, asset_in, amount_out)
+			.map_err(|e| runtime_error("Unable to query the runtime.", e))?
+			.map_err(quote_error)
+	}
+
+	fn get_pool(
+		&self,
+		swap_id: SwapId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<PoolInfo<AccountId, TokenId, TokenBalance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_pool(&at, swap_id)
+			.map_err(|e| runtime_error("Unable to query the pool.", e))
+	}
+
+	fn list_pools(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(SwapId, PoolInfo<AccountId, TokenId, TokenBalance>)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.list_pools(&at)
+			.map_err(|e| runtime_error("Unable to query the pools.", e))
+	}
+}
+
+/// Implements the `FungibleApi` RPC trait for interacting with the tokens.
+pub struct Fungible<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Fungible<C, B> {
+	/// Creates a new instance of the Fungible RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Fungible { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId, TokenId, TokenBalance>
+	FungibleApi<<Block as BlockT>::Hash, AccountId, TokenId, TokenBalance>
+	for Fungible<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: FungibleRuntimeApi<Block, AccountId, TokenId, TokenBalance>,
+	AccountId: Codec,
+	TokenId: Codec,
+	TokenBalance: Codec,
+{
+	fn balance_of(
+		&self,
+		token_id: TokenId,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<TokenBalance> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.balance_of(&at, token_id, who)
+			.map_err(|e| runtime_error("Unable to query the balance.", e))
+	}
+
+	fn total_supply(
+		&self,
+		token_id: TokenId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<TokenBalance> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.total_supply(&at, token_id)
+			.map_err(|e| runtime_error("Unable to query the total supply.", e))
+	}
+}
@@ -0,0 +1,436 @@
+//! Swaps pallet benchmarking.
+
+use super::*;
+
+use system::RawOrigin;
+use frame_benchmarking::{benchmarks, account};
+use sp_runtime::traits::Bounded;
+
+const SEED: u32 = 0;
+const MAX_USER_INDEX: u32 = 1000;
+
+/// Funds `who` with currency and a fresh token, returning the token id.
+fn funded_token<T: Trait>(who: &T::AccountId) -> T::TokenId {
+	let _ = T::Currency::make_free_balance_be(who, 1_000_000_000u32.into());
+	fungible::Module::<T>::create_token(who.clone(), 1_000_000_000u32.into())
+}
+
+/// Creates a swap for a fresh token with liquidity provided by `who`.
+fn swap_with_liquidity<T: Trait>(who: &T::AccountId) -> (T::SwapId, T::TokenId) {
+	let token_id = funded_token::<T>(who);
+	let swap_id = Module::<T>::swap_count();
+	Module::<T>::create_swap(RawOrigin::Signed(who.clone()).into(), token_id)
+		.expect("swap can be created");
+	Module::<T>::add_liquidity(
+		RawOrigin::Signed(who.clone()).into(),
+		swap_id,
+		1_000_000u32.into(),
+		Zero::zero(),
+		1_000_000u32.into(),
+		T::BlockNumber::max_value(),
+	).expect("liquidity can be added");
+	(swap_id, token_id)
+}
+
+/// Creates a swap with a running farm funded with its own reward token, and
+/// stakes half of the shares of `who` in it.
+fn farm_with_stake<T: Trait>(who: &T::AccountId) -> T::SwapId {
+	let (swap_id, _) = swap_with_liquidity::<T>(who);
+	let reward_token = fungible::Module::<T>::create_token(who.clone(), 1_000_000_000u32.into());
+	Module::<T>::create_farm(
+		RawOrigin::Root.into(),
+		swap_id,
+		reward_token,
+		1_000u32.into(),
+		Zero::zero(),
+		T::BlockNumber::max_value(),
+	).expect("farm can be created");
+	Module::<T>::fund_farm(RawOrigin::Signed(who.clone()).into(), swap_id, 1_000_000u32.into())
+		.expect("farm can be funded");
+	Module::<T>::stake(RawOrigin::Signed(who.clone()).into(), swap_id, 500_000u32.into())
+		.expect("shares can be staked");
+	swap_id
+}
+
+/// Creates a concentrated liquidity swap for a fresh token at a price of one
+/// with a position provided by `who` around it.
+fn concentrated_swap_with_position<T: Trait>(who: &T::AccountId) -> (T::SwapId, T::PositionId) {
+	let token_id = funded_token::<T>(who);
+	let swap_id = Module::<T>::swap_count();
+	Module::<T>::create_swap(RawOrigin::Signed(who.clone()).into(), token_id)
+		.expect("swap can be created");
+	Module::<T>::set_swap_concentrated(RawOrigin::Root.into(), swap_id, 10, U256::one() << 96)
+		.expect("curve can be set");
+	let position_id = Module::<T>::position_count();
+	Module::<T>::mint_position(
+		RawOrigin::Signed(who.clone()).into(),
+		swap_id,
+		-100,
+		100,
+		1_000_000,
+		1_000_000u32.into(),
+		1_000_000u32.into(),
+		T::BlockNumber::max_value(),
+	).expect("position can be minted");
+	(swap_id, position_id)
+}
+
+benchmarks! {
+	_ {
+		let u in 1 .. MAX_USER_INDEX => ();
+	}
+
+	create_swap {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let token_id = funded_token::<T>(&caller);
+	}: _(RawOrigin::Signed(caller), token_id)
+
+	set_swap_fee {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(RawOrigin::Root, swap_id, Some(Permill::from_percent(1)))
+
+	set_swap_curve {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let token_id = funded_token::<T>(&caller);
+		let swap_id = Module::<T>::swap_count();
+		Module::<T>::create_swap(RawOrigin::Signed(caller).into(), token_id)
+			.expect("swap can be created");
+	}: _(RawOrigin::Root, swap_id, Some(100))
+
+	ramp_amplification {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let token_id = funded_token::<T>(&caller);
+		let swap_id = Module::<T>::swap_count();
+		Module::<T>::create_swap(RawOrigin::Signed(caller).into(), token_id)
+			.expect("swap can be created");
+		Module::<T>::set_swap_curve(RawOrigin::Root.into(), swap_id, Some(100))
+			.expect("curve can be set");
+	}: _(RawOrigin::Root, swap_id, 1_000, T::BlockNumber::max_value())
+
+	claim_protocol_fees {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+		<Swaps<T>>::mutate(swap_id, |maybe_swap| {
+			if let Some(swap) = maybe_swap {
+				swap.protocol_base = 1_000u32.into();
+				swap.protocol_tokens = 1_000u32.into();
+			}
+		});
+	}: _(RawOrigin::Signed(caller), swap_id)
+
+	// Worst case: the swap already has liquidity, so shares are priced.
+	add_liquidity {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller),
+		swap_id,
+		1_000u32.into(),
+		One::one(),
+		1_000_000u32.into(),
+		T::BlockNumber::max_value()
+	)
+
+	// Worst case: a StableSwap swap at the highest amplification, which takes
+	// `MAX_SINGLE_SIDED_STEPS` quotes that each solve its invariant.
+	add_liquidity_single_sided {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let token_id = funded_token::<T>(&caller);
+		let swap_id = Module::<T>::swap_count();
+		Module::<T>::create_swap(RawOrigin::Signed(caller.clone()).into(), token_id)
+			.expect("swap can be created");
+		Module::<T>::set_swap_curve(RawOrigin::Root.into(), swap_id, Some(MAX_AMPLIFICATION))
+			.expect("curve can be set");
+		Module::<T>::add_liquidity(
+			RawOrigin::Signed(caller.clone()).into(),
+			swap_id,
+			1_000_000u32.into(),
+			Zero::zero(),
+			1_000_000u32.into(),
+			T::BlockNumber::max_value(),
+		).expect("liquidity can be added");
+		fungible::Module::<T>::mint(token_id, caller.clone(), 4_000_000_000u32.into())
+			.expect("tokens can be minted");
+	}: _(
+		RawOrigin::Signed(caller),
+		swap_id,
+		Asset::Token(token_id),
+		4_000_000_000u32.into(),
+		One::one(),
+		T::BlockNumber::max_value()
+	)
+
+	remove_liquidity {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller),
+		swap_id,
+		1_000u32.into(),
+		One::one(),
+		One::one(),
+		T::BlockNumber::max_value()
+	)
+
+	remove_liquidity_one_side {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller),
+		swap_id,
+		1_000u32.into(),
+		Side::Base,
+		One::one(),
+		T::BlockNumber::max_value()
+	)
+
+	currency_to_tokens_input {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		swap_id,
+		1_000u32.into(),
+		One::one(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	currency_to_tokens_output {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		swap_id,
+		1_000u32.into(),
+		10_000u32.into(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	tokens_to_currency_input {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		swap_id,
+		1_000u32.into(),
+		One::one(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	tokens_to_currency_output {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		swap_id,
+		1_000u32.into(),
+		10_000u32.into(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	swap_exact_in_along_path {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (first, token_id) = swap_with_liquidity::<T>(&caller);
+		let (second, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		vec![first, second],
+		Asset::Token(token_id),
+		1_000u32.into(),
+		One::one(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	swap_exact_out_along_path {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (first, token_id) = swap_with_liquidity::<T>(&caller);
+		let (second, _) = swap_with_liquidity::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		vec![first, second],
+		Asset::Token(token_id),
+		1_000u32.into(),
+		10_000u32.into(),
+		T::BlockNumber::max_value(),
+		caller
+	)
+
+	// Worst case: the loan is not repaid and is taken back from the caller.
+	// The runtime's `FlashSwapHandler` is given no data to repay with and
+	// its own weight is added on top, so the result of the call is ignored.
+	flash_swap {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: {
+		let _ = Module::<T>::flash_swap(
+			RawOrigin::Signed(caller).into(),
+			swap_id,
+			1_000u32.into(),
+			1_000u32.into(),
+			Vec::new(),
+		);
+	}
+
+	// Worst case: both ticks of the new position are initialised.
+	mint_position {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = concentrated_swap_with_position::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller),
+		swap_id,
+		-200,
+		200,
+		1_000_000,
+		1_000_000u32.into(),
+		1_000_000u32.into(),
+		T::BlockNumber::max_value()
+	)
+
+	// Worst case: the whole position is burned and its ticks cleared.
+	burn_position {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (_, position_id) = concentrated_swap_with_position::<T>(&caller);
+	}: _(
+		RawOrigin::Signed(caller),
+		position_id,
+		1_000_000,
+		Zero::zero(),
+		Zero::zero(),
+		T::BlockNumber::max_value()
+	)
+
+	collect_position_fees {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, position_id) = concentrated_swap_with_position::<T>(&caller);
+		Module::<T>::currency_to_tokens_input(
+			RawOrigin::Signed(caller.clone()).into(),
+			swap_id,
+			1_000u32.into(),
+			One::one(),
+			T::BlockNumber::max_value(),
+			caller.clone(),
+		).expect("trade succeeds");
+	}: _(RawOrigin::Signed(caller), position_id)
+
+	sync {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(RawOrigin::Signed(caller), swap_id)
+
+	// Worst case: both assets were sent to the swap account.
+	skim {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, token_id) = swap_with_liquidity::<T>(&caller);
+		let swap = Module::<T>::swaps(swap_id).expect("swap exists");
+		T::Currency::transfer(&caller, &swap.account, 1_000u32.into(), ExistenceRequirement::KeepAlive)
+			.expect("currency can be sent");
+		fungible::Module::<T>::do_transfer(token_id, caller.clone(), swap.account, 1_000u32.into())
+			.expect("tokens can be sent");
+	}: _(RawOrigin::Signed(caller.clone()), swap_id, caller)
+
+	create_farm {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+		let (swap_id, token_id) = swap_with_liquidity::<T>(&caller);
+	}: _(RawOrigin::Root, swap_id, token_id, 1_000u32.into(), Zero::zero(), T::BlockNumber::max_value())
+
+	fund_farm {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let swap_id = farm_with_stake::<T>(&caller);
+	}: _(RawOrigin::Signed(caller), swap_id, 1_000u32.into())
+
+	// Worst case: the stake is settled after rewards have accrued.
+	stake {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let swap_id = farm_with_stake::<T>(&caller);
+		system::Module::<T>::set_block_number(10u32.into());
+	}: _(RawOrigin::Signed(caller), swap_id, 1_000u32.into())
+
+	unstake {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let swap_id = farm_with_stake::<T>(&caller);
+		system::Module::<T>::set_block_number(10u32.into());
+	}: _(RawOrigin::Signed(caller), swap_id, 1_000u32.into())
+
+	harvest {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let swap_id = farm_with_stake::<T>(&caller);
+		system::Module::<T>::set_block_number(10u32.into());
+	}: _(RawOrigin::Signed(caller), swap_id)
+
+	place_limit_order {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+	}: _(RawOrigin::Signed(caller), swap_id, Side::Base, 1_000u32.into(), 1, T::BlockNumber::max_value())
+
+	// Worst case: the keeper is paid a tip.
+	execute_order {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+		let order_id = Module::<T>::order_count();
+		Module::<T>::place_limit_order(
+			RawOrigin::Signed(caller.clone()).into(),
+			swap_id,
+			Side::Base,
+			1_000_000u32.into(),
+			1,
+			T::BlockNumber::max_value(),
+		).expect("order can be placed");
+	}: _(RawOrigin::Signed(caller), order_id)
+
+	cancel_order {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+		let order_id = Module::<T>::order_count();
+		Module::<T>::place_limit_order(
+			RawOrigin::Signed(caller.clone()).into(),
+			swap_id,
+			Side::Base,
+			1_000u32.into(),
+			1,
+			T::BlockNumber::max_value(),
+		).expect("order can be placed");
+	}: _(RawOrigin::Signed(caller), order_id)
+
+	// Worst case: resuming a swap also clears its reference price.
+	set_swap_paused {
+		let u in ...;
+		let caller = account("caller", u, SEED);
+		let (swap_id, _) = swap_with_liquidity::<T>(&caller);
+		Module::<T>::set_swap_paused(RawOrigin::Root.into(), swap_id, true).expect("swap can be paused");
+	}: _(RawOrigin::Root, swap_id, false)
+}
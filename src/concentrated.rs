@@ -0,0 +1,303 @@
+//! Concentrated liquidity math, after Uniswap v3's `TickMath`,
+//! `SqrtPriceMath`, `SwapMath` and `TickBitmap`.
+//!
+//! Prices are in tokens per base asset, so selling the base asset moves the
+//! price down. Square roots of prices are Q64.96 fixed point numbers and tick
+//! `i` is the price `1.0001^i`. Amounts are rounded in favour of the swap and
+//! every function returns `None` if a value overflows.
+
+use sp_core::{U256, U512};
+use sp_std::convert::TryFrom;
+
+/// The lowest tick a price may be in.
+pub const MIN_TICK: i32 = -887_272;
+
+/// The highest tick a price may be in.
+pub const MAX_TICK: i32 = 887_272;
+
+/// The widest spacing between the ticks positions may use.
+pub const MAX_TICK_SPACING: u32 = 16_384;
+
+/// `2^128 / sqrt(1.0001)^(2^i)` for bits 1 to 19 of a tick, as Q128.128.
+const TICK_FACTORS: [u128; 19] = [
+	0xfff97272373d413259a46990580e213a,
+	0xfff2e50f5f656932ef12357cf3c7fdcc,
+	0xffe5caca7e10e4e61c3624eaa0941cd0,
+	0xffcb9843d60f6159c9db58835c926644,
+	0xff973b41fa98c081472e6896dfb254c0,
+	0xff2ea16466c96a3843ec78b326b52861,
+	0xfe5dee046a99a2a811c461f1969c3053,
+	0xfcbe86c7900a88aedcffc83b479aa3a4,
+	0xf987a7253ac413176f2b074cf7815e54,
+	0xf3392b0822b70005940c7a398e4b70f3,
+	0xe7159475a2c29b7443b29c7fa6e889d9,
+	0xd097f3bdfd2022b8845ad8f792aa5825,
+	0xa9f746462d870fdf8a65dc1f90e061e5,
+	0x70d869a156d2a1b890bb3df62baf32f7,
+	0x31be135f97d08fd981231505542fcfa6,
+	0x9aa508b5b7a84e1c677de54f3e99bc9,
+	0x5d6af8dedb81196699c329225ee604,
+	0x2216e584f5fa1ea926041bedfe98,
+	0x48a170391f7dc42444e8fa2,
+];
+
+/// The square root price at `MIN_TICK`.
+pub fn min_sqrt_price() -> U256 {
+	U256::from(4_295_128_739u64)
+}
+
+/// The square root price at `MAX_TICK`.
+pub fn max_sqrt_price() -> U256 {
+	U256([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0])
+}
+
+/// The square root price at `tick`.
+pub fn sqrt_price_at_tick(tick: i32) -> Option<U256> {
+	if tick < MIN_TICK || tick > MAX_TICK {
+		return None;
+	}
+
+	let abs_tick = i64::from(tick).abs() as u32;
+	let mut ratio = if abs_tick & 1 != 0 {
+		U256::from(0xfffcb933bd6fad37aa2d162d1a594001u128)
+	} else {
+		U256::one() << 128
+	};
+	for (i, factor) in TICK_FACTORS.iter().enumerate() {
+		if abs_tick & (1 << (i + 1)) != 0 {
+			ratio = ratio.checked_mul(U256::from(*factor))? >> 128;
+		}
+	}
+	if tick > 0 {
+		ratio = U256::max_value() / ratio;
+	}
+
+	// Q128.128 to Q64.96, rounding up.
+	let remainder = ratio & U256::from(u32::max_value());
+	Some((ratio >> 32) + if remainder.is_zero() { U256::zero() } else { U256::one() })
+}
+
+/// The highest tick whose square root price is at most `sqrt_price`.
+pub fn tick_at_sqrt_price(sqrt_price: U256) -> Option<i32> {
+	if sqrt_price < min_sqrt_price() || sqrt_price >= max_sqrt_price() {
+		return None;
+	}
+
+	let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+	while low < high {
+		let middle = low + (high - low + 1) / 2;
+		if sqrt_price_at_tick(middle)? <= sqrt_price {
+			low = middle;
+		} else {
+			high = middle - 1;
+		}
+	}
+	Some(low)
+}
+
+/// The base asset held by `liquidity` between two square root prices.
+pub fn base_delta(sqrt_price_a: U256, sqrt_price_b: U256, liquidity: u128, round_up: bool) -> Option<U256> {
+	let (lower, upper) = sorted(sqrt_price_a, sqrt_price_b);
+	if lower.is_zero() {
+		return None;
+	}
+
+	let numerator = U256::from(liquidity) << 96;
+	if round_up {
+		div_up(mul_div_up(numerator, upper - lower, upper)?, lower)
+	} else {
+		mul_div(numerator, upper - lower, upper)?.checked_div(lower)
+	}
+}
+
+/// The tokens held by `liquidity` between two square root prices.
+pub fn token_delta(sqrt_price_a: U256, sqrt_price_b: U256, liquidity: u128, round_up: bool) -> Option<U256> {
+	let (lower, upper) = sorted(sqrt_price_a, sqrt_price_b);
+	if round_up {
+		mul_div_up(U256::from(liquidity), upper - lower, q96())
+	} else {
+		mul_div(U256::from(liquidity), upper - lower, q96())
+	}
+}
+
+/// The result of trading within a single range of liquidity.
+pub struct SwapStep {
+	/// The square root price after the step.
+	pub sqrt_price: U256,
+	/// The amount sold, excluding the fee.
+	pub amount_in: U256,
+	/// The amount bought.
+	pub amount_out: U256,
+	/// The fee paid on top of `amount_in`.
+	pub fee_amount: U256,
+}
+
+/// Trades from `sqrt_price` towards `sqrt_price_target` with `liquidity`,
+/// stopping early once `amount_remaining` is used up.
+///
+/// `amount_remaining` is the amount left to sell, including the fee, when
+/// `exact_in` and the amount left to buy otherwise. `fee` is in parts per
+/// million.
+pub fn swap_step(
+	sqrt_price: U256,
+	sqrt_price_target: U256,
+	liquidity: u128,
+	amount_remaining: U256,
+	exact_in: bool,
+	fee: u32,
+) -> Option<SwapStep> {
+	let base_in = sqrt_price >= sqrt_price_target;
+	let million = U256::from(1_000_000u32);
+	let fee = U256::from(fee);
+	let fee_complement = million.checked_sub(fee)?;
+
+	let amount_in_to = |next: U256| if base_in {
+		base_delta(next, sqrt_price, liquidity, true)
+	} else {
+		token_delta(sqrt_price, next, liquidity, true)
+	};
+	let amount_out_to = |next: U256| if base_in {
+		token_delta(next, sqrt_price, liquidity, false)
+	} else {
+		base_delta(sqrt_price, next, liquidity, false)
+	};
+
+	let next = if exact_in {
+		let remaining_less_fee = mul_div(amount_remaining, fee_complement, million)?;
+		if remaining_less_fee >= amount_in_to(sqrt_price_target)? {
+			sqrt_price_target
+		} else {
+			next_sqrt_price_from_input(sqrt_price, liquidity, remaining_less_fee, base_in)?
+		}
+	} else if amount_remaining >= amount_out_to(sqrt_price_target)? {
+		sqrt_price_target
+	} else {
+		next_sqrt_price_from_output(sqrt_price, liquidity, amount_remaining, base_in)?
+	};
+
+	let amount_in = amount_in_to(next)?;
+	let mut amount_out = amount_out_to(next)?;
+	if !exact_in && amount_out > amount_remaining {
+		amount_out = amount_remaining;
+	}
+	// What is left of an exact input that stops short of the target is all
+	// taken as the fee.
+	let fee_amount = if exact_in && next != sqrt_price_target {
+		amount_remaining.saturating_sub(amount_in)
+	} else {
+		mul_div_up(amount_in, fee, fee_complement)?
+	};
+
+	Some(SwapStep { sqrt_price: next, amount_in, amount_out, fee_amount })
+}
+
+/// The square root price after selling `amount` with `liquidity`.
+fn next_sqrt_price_from_input(sqrt_price: U256, liquidity: u128, amount: U256, base_in: bool) -> Option<U256> {
+	if base_in {
+		next_sqrt_price_from_base(sqrt_price, liquidity, amount, true)
+	} else {
+		next_sqrt_price_from_tokens(sqrt_price, liquidity, amount, true)
+	}
+}
+
+/// The square root price after buying `amount` with `liquidity`.
+fn next_sqrt_price_from_output(sqrt_price: U256, liquidity: u128, amount: U256, base_in: bool) -> Option<U256> {
+	if base_in {
+		next_sqrt_price_from_tokens(sqrt_price, liquidity, amount, false)
+	} else {
+		next_sqrt_price_from_base(sqrt_price, liquidity, amount, false)
+	}
+}
+
+/// The square root price after adding or removing `amount` of the base
+/// asset, rounded up.
+fn next_sqrt_price_from_base(sqrt_price: U256, liquidity: u128, amount: U256, add: bool) -> Option<U256> {
+	if amount.is_zero() {
+		return Some(sqrt_price);
+	}
+
+	let numerator = U256::from(liquidity) << 96;
+	let product = amount.checked_mul(sqrt_price)?;
+	let denominator = if add { numerator.checked_add(product)? } else { numerator.checked_sub(product)? };
+	mul_div_up(numerator, sqrt_price, denominator)
+}
+
+/// The square root price after adding or removing `amount` of tokens,
+/// rounded down.
+fn next_sqrt_price_from_tokens(sqrt_price: U256, liquidity: u128, amount: U256, add: bool) -> Option<U256> {
+	if add {
+		sqrt_price.checked_add(mul_div(amount, q96(), U256::from(liquidity))?)
+	} else {
+		sqrt_price.checked_sub(mul_div_up(amount, q96(), U256::from(liquidity))?)
+	}
+}
+
+/// The tick of the bitmap of a swap with `tick_spacing`, rounded down.
+pub fn compress(tick: i32, tick_spacing: i32) -> i32 {
+	let compressed = tick / tick_spacing;
+	if tick < 0 && tick % tick_spacing != 0 { compressed - 1 } else { compressed }
+}
+
+/// The word of the tick bitmap a compressed tick is in and its bit in it.
+pub fn bitmap_position(compressed: i32) -> (i16, u8) {
+	((compressed >> 8) as i16, (compressed & 0xff) as u8)
+}
+
+/// The highest set bit of `word` at or below `bit`.
+pub fn highest_bit_at_or_below(word: U256, bit: u8) -> Option<u8> {
+	let mask = if bit == 255 { U256::max_value() } else { (U256::one() << (bit as usize + 1)) - 1 };
+	let masked = word & mask;
+	if masked.is_zero() { None } else { Some((masked.bits() - 1) as u8) }
+}
+
+/// The lowest set bit of `word` at or above `bit`.
+pub fn lowest_bit_at_or_above(word: U256, bit: u8) -> Option<u8> {
+	let masked = word & !((U256::one() << bit as usize) - 1);
+	if masked.is_zero() { None } else { Some(masked.trailing_zeros() as u8) }
+}
+
+/// The base asset price in tokens and the token price in the base asset at
+/// `sqrt_price`, scaled by `precision`.
+pub fn spot_prices(sqrt_price: U256, precision: U256) -> Option<(U256, U256)> {
+	let price = sqrt_price.full_mul(sqrt_price);
+	let base_price = (price * U512::from(precision)) >> 192;
+	let token_price = (U512::from(precision) << 192).checked_div(price)?;
+	Some((U256::try_from(base_price).ok()?, U256::try_from(token_price).ok()?))
+}
+
+/// Computes `a * b / denominator` without overflowing in the product.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+	let quotient = a.full_mul(b).checked_div(U512::from(denominator))?;
+	U256::try_from(quotient).ok()
+}
+
+/// Computes `a * b / denominator`, rounded up.
+pub fn mul_div_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+	let product = a.full_mul(b);
+	let denominator = U512::from(denominator);
+	let quotient = product.checked_div(denominator)?;
+	let quotient = if (product % denominator).is_zero() { quotient } else { quotient + U512::one() };
+	U256::try_from(quotient).ok()
+}
+
+/// Adds a signed change to an amount of liquidity.
+pub fn add_liquidity_delta(liquidity: u128, delta: i128) -> Option<u128> {
+	if delta < 0 {
+		liquidity.checked_sub(delta.checked_neg()? as u128)
+	} else {
+		liquidity.checked_add(delta as u128)
+	}
+}
+
+fn div_up(a: U256, b: U256) -> Option<U256> {
+	let quotient = a.checked_div(b)?;
+	if (a % b).is_zero() { Some(quotient) } else { quotient.checked_add(U256::one()) }
+}
+
+fn sorted(a: U256, b: U256) -> (U256, U256) {
+	if a <= b { (a, b) } else { (b, a) }
+}
+
+fn q96() -> U256 {
+	U256::one() << 96
+}
@@ -1,19 +1,34 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Encode, Decode};
-use sp_runtime::{ModuleId};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use sp_std::{prelude::*, convert::{TryFrom, TryInto}};
+use sp_core::{U256, U512};
+use sp_runtime::{ModuleId, RuntimeDebug, DispatchError, Permill};
 use sp_runtime::traits::{
-    Member, One, Zero, AtLeast32Bit, MaybeSerializeDeserialize, CheckedAdd,
-    AccountIdConversion, SaturatedConversion,
+    Member, One, Zero, AtLeast32Bit, MaybeSerializeDeserialize, CheckedAdd, CheckedSub,
+    AccountIdConversion, SaturatedConversion, Saturating, Bounded,
 };
 
 use frame_support::{decl_module, decl_storage, decl_event, decl_error, dispatch,
-	ensure, Parameter, traits::{Currency, ExistenceRequirement},
+	ensure, Parameter, traits::{Currency, EnsureOrigin, ExistenceRequirement, Get, WithdrawReason},
+	weights::{SimpleDispatchInfo, FunctionOf, DispatchClass, Weight},
 };
-use system::ensure_signed;
+use system::{ensure_signed, ensure_root};
 
 use pallet_fungible::{self as fungible};
 
+mod weights;
+pub use weights::WeightInfo;
+
+mod stable_swap;
+mod weighted;
+mod concentrated;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
 #[cfg(test)]
 mod mock;
 
@@ -22,20 +37,400 @@ mod tests;
 
 #[derive(Clone, Eq, PartialEq, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct Swap<AccountId, TokenId> {
+pub struct Swap<AccountId, TokenId, TokenBalance, BlockNumber> {
+	// The asset the token is priced in, the native currency unless the swap
+	// was created for a pair of tokens.
+	base: Asset<TokenId>,
 	// The token being swapped.
 	token_id: TokenId,
 	// The "swap token" id.
 	swap_token: TokenId,
 	// This swap account.
 	account: AccountId,
+	// Overrides the default liquidity provider fee, if set.
+	fee: Option<Permill>,
+	// The base asset the swap trades against, tracked apart from its balance
+	// so that transfers straight into the swap account cannot move its price.
+	base_reserve: TokenBalance,
+	// The tokens the swap trades against.
+	token_reserve: TokenBalance,
+	// Protocol fees taken in the base asset and not yet claimed.
+	protocol_base: TokenBalance,
+	// Protocol fees taken in tokens and not yet claimed.
+	protocol_tokens: TokenBalance,
+	// Sum of the token price in the base asset over every block, wrapping on
+	// overflow.
+	token_price_cumulative: U256,
+	// Sum of the base asset price in tokens over every block, wrapping on
+	// overflow.
+	base_price_cumulative: U256,
+	// The block the price accumulators were last updated in.
+	price_updated: BlockNumber,
+	// The invariant trades are priced with.
+	curve: Curve<BlockNumber>,
+}
+
+/// A snapshot of the price accumulators of a swap.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct PriceObservation<BlockNumber> {
+	/// The block the snapshot was taken in.
+	pub block: BlockNumber,
+	/// The cumulative token price in the base asset.
+	pub token_price_cumulative: U256,
+	/// The cumulative base asset price in tokens.
+	pub base_price_cumulative: U256,
+}
+
+/// An asset that can be traded through a swap.
+///
+/// Assets are ordered with the native currency first, so it is always the
+/// base asset of the swaps it is part of.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Asset<TokenId> {
+	/// The native currency, as a pseudo-asset.
+	Currency,
+	/// A token of the fungible pallet.
+	Token(TokenId),
+}
+
+/// One of the two assets of a swap.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Side {
+	/// The base asset.
+	Base,
+	/// The token being swapped.
+	Token,
+}
+
+/// The invariant a swap prices trades with.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum Curve<BlockNumber> {
+	/// Uniswap's constant product, `x * y = k`.
+	ConstantProduct,
+	/// Curve's StableSwap invariant for pegged assets. The amplification
+	/// moves linearly from `initial_a` at `ramp_start` to `future_a` at
+	/// `ramp_end`.
+	StableSwap {
+		initial_a: u32,
+		future_a: u32,
+		ramp_start: BlockNumber,
+		ramp_end: BlockNumber,
+	},
+	/// Balancer's weighted product, `x^wx * y^wy = k`, with `base_weight` of
+	/// the value of the swap held in the base asset and the rest in tokens.
+	Weighted {
+		base_weight: Permill,
+	},
+	/// Uniswap v3's concentrated liquidity, with liquidity provided by
+	/// positions over ranges of ticks that are multiples of `tick_spacing`.
+	Concentrated {
+		tick_spacing: u32,
+	},
+}
+
+/// The state of a concentrated liquidity swap.
+#[derive(Clone, Default, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ConcentratedPool {
+	/// The square root of the price in tokens per base asset, as Q64.96.
+	pub sqrt_price: U256,
+	/// The tick the price is in.
+	pub tick: i32,
+	/// The liquidity of the positions in range of the price.
+	pub liquidity: u128,
+	/// The base asset fees earned per unit of liquidity, as Q128.128 and
+	/// wrapping on overflow.
+	pub fee_growth_base: U256,
+	/// The token fees earned per unit of liquidity, as Q128.128 and wrapping
+	/// on overflow.
+	pub fee_growth_tokens: U256,
+}
+
+/// A tick of a concentrated liquidity swap that bounds at least one position.
+#[derive(Clone, Default, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct Tick {
+	/// The liquidity of the positions bounded by the tick.
+	pub liquidity_gross: u128,
+	/// The liquidity added when the price moves up through the tick.
+	pub liquidity_net: i128,
+	/// The base asset fee growth on the other side of the tick from the
+	/// price.
+	pub fee_growth_outside_base: U256,
+	/// The token fee growth on the other side of the tick from the price.
+	pub fee_growth_outside_tokens: U256,
+}
+
+/// Liquidity provided to a concentrated liquidity swap over a range of ticks.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct Position<AccountId, SwapId, TokenBalance> {
+	/// The account the position belongs to.
+	pub owner: AccountId,
+	/// The swap the liquidity is provided to.
+	pub swap_id: SwapId,
+	/// The lowest tick the liquidity is used at.
+	pub tick_lower: i32,
+	/// The tick the liquidity stops being used at.
+	pub tick_upper: i32,
+	/// The liquidity of the position.
+	pub liquidity: u128,
+	/// The base asset fee growth within the range when fees were last
+	/// settled.
+	pub fee_growth_inside_base: U256,
+	/// The token fee growth within the range when fees were last settled.
+	pub fee_growth_inside_tokens: U256,
+	/// Settled base asset fees not yet collected.
+	pub base_owed: TokenBalance,
+	/// Settled token fees not yet collected.
+	pub tokens_owed: TokenBalance,
+}
+
+/// A schedule of rewards for staking the liquidity shares of a swap.
+///
+/// Every block after `start` up to and including `end` shares
+/// `reward_per_block` between the shares staked at the time.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct Farm<TokenId, TokenBalance, BlockNumber> {
+	/// The token rewards are paid in.
+	pub reward_token: TokenId,
+	/// The rewards shared between stakers in every block.
+	pub reward_per_block: TokenBalance,
+	/// The block rewards start accruing after.
+	pub start: BlockNumber,
+	/// The last block rewards accrue in.
+	pub end: BlockNumber,
+	/// The shares staked in the farm.
+	pub total_staked: TokenBalance,
+	/// The rewards earned per staked share, as Q128.128 and wrapping on
+	/// overflow.
+	pub reward_per_share: U256,
+	/// The block rewards have been accounted for up to.
+	pub reward_updated: BlockNumber,
+}
+
+/// An order to sell `amount` of one side of a swap once it fetches at least
+/// `limit_price`, held in escrow until it is executed or cancelled.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct LimitOrder<AccountId, SwapId, TokenBalance, BlockNumber> {
+	/// The account the order belongs to.
+	pub owner: AccountId,
+	/// The swap the order is filled against.
+	pub swap_id: SwapId,
+	/// The side of the swap being sold.
+	pub side: Side,
+	/// The amount being sold, including the keeper's tip.
+	pub amount: TokenBalance,
+	/// The least of the other side to receive per unit of `amount`, scaled
+	/// by `PRICE_PRECISION`.
+	pub limit_price: u128,
+	/// The last block the order can be executed in.
+	pub expiry: BlockNumber,
+}
+
+/// The liquidity shares an account has staked in a farm.
+#[derive(Clone, Default, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct Stake<TokenBalance> {
+	/// The shares staked.
+	pub amount: TokenBalance,
+	/// The rewards per share of the farm when the stake was last settled.
+	pub reward_per_share: U256,
+	/// Settled rewards not yet harvested.
+	pub rewards_owed: TokenBalance,
+}
+
+/// A summary of a swap and its reserves, as returned to clients.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PoolInfo<AccountId, TokenId, TokenBalance> {
+	/// The asset the token is priced in.
+	pub base: Asset<TokenId>,
+	/// The token being swapped.
+	pub token_id: TokenId,
+	/// The token minted as liquidity shares.
+	pub swap_token: TokenId,
+	/// The swap account.
+	pub account: AccountId,
+	/// The base asset reserve.
+	pub base_reserve: TokenBalance,
+	/// The token reserve.
+	pub token_reserve: TokenBalance,
+	/// The total supply of liquidity shares.
+	pub total_shares: TokenBalance,
+	/// The liquidity provider fee charged on trades.
+	pub fee: Permill,
+	/// The current StableSwap amplification, or `None` for a constant
+	/// product swap.
+	pub amplification: Option<u32>,
+	/// The weight of the base asset, or `None` unless the swap is weighted.
+	pub base_weight: Option<Permill>,
+	/// The state of a concentrated liquidity swap, or `None` for any other.
+	pub concentrated: Option<ConcentratedPool>,
+}
+
+/// A single leg of a route through the swaps.
+struct Hop<T: Trait> {
+	swap_id: T::SwapId,
+	swap: SwapOf<T>,
+	asset_in: Asset<T::TokenId>,
+	asset_out: Asset<T::TokenId>,
+	amount_in: T::TokenBalance,
+	amount_out: T::TokenBalance,
+	// Whether `amount_in` was given and `amount_out` quoted from it, rather
+	// than the other way around.
+	exact_in: bool,
+}
+
+/// The outcome of a trade against a concentrated liquidity swap.
+struct ConcentratedTrade {
+	amount_in: U256,
+	amount_out: U256,
+	// The share of the fee set aside for the protocol.
+	protocol_fee: U256,
+	// The state of the swap after the trade.
+	pool: ConcentratedPool,
+	// The ticks crossed, with the global fee growth at the time.
+	crossed: Vec<(i32, U256, U256)>,
+}
+
+/// Converts amounts between the currency balance and the token balance.
+///
+/// Both directions fail instead of saturating when an amount has no
+/// representation in the target type.
+pub trait BalanceConvert<Balance, TokenBalance> {
+	/// Converts a currency amount into a token amount.
+	fn to_token_balance(balance: Balance) -> Option<TokenBalance>;
+	/// Converts a token amount into a currency amount.
+	fn to_balance(token_balance: TokenBalance) -> Option<Balance>;
+}
+
+/// Converts between any two balance types through `u128`.
+pub struct CheckedConvert;
+
+impl<Balance, TokenBalance> BalanceConvert<Balance, TokenBalance> for CheckedConvert
+where
+	Balance: TryInto<u128> + TryFrom<u128>,
+	TokenBalance: TryInto<u128> + TryFrom<u128>,
+{
+	fn to_token_balance(balance: Balance) -> Option<TokenBalance> {
+		let value: u128 = balance.try_into().ok()?;
+		TokenBalance::try_from(value).ok()
+	}
+
+	fn to_balance(token_balance: TokenBalance) -> Option<Balance> {
+		let value: u128 = token_balance.try_into().ok()?;
+		Balance::try_from(value).ok()
+	}
+}
+
+/// Receives the funds lent out by a flash swap.
+pub trait FlashSwapHandler<AccountId, SwapId, TokenBalance> {
+	/// Called once `base` of the base asset and `tokens` have been sent from
+	/// `swap_account` to `borrower`, with the `data` passed to `flash_swap`.
+	///
+	/// Before returning, the swap account must be paid back so that the
+	/// invariant of its reserves, less the fee on what was paid in, is no
+	/// lower than before the loan.
+	fn on_flash_swap(
+		swap_id: SwapId,
+		borrower: &AccountId,
+		swap_account: &AccountId,
+		base: TokenBalance,
+		tokens: TokenBalance,
+		data: &[u8],
+	) -> dispatch::DispatchResult;
+
+	/// The weight of `on_flash_swap` for a loan of `base` and `tokens` with
+	/// `data`, which is added to the weight of `flash_swap`.
+	fn weight(base: TokenBalance, tokens: TokenBalance, data: &[u8]) -> Weight;
+}
+
+impl<AccountId, SwapId, TokenBalance> FlashSwapHandler<AccountId, SwapId, TokenBalance> for () {
+	fn on_flash_swap(_: SwapId, _: &AccountId, _: &AccountId, _: TokenBalance, _: TokenBalance, _: &[u8])
+		-> dispatch::DispatchResult
+	{
+		Err(DispatchError::Other("No flash swap handler"))
+	}
+
+	fn weight(_: TokenBalance, _: TokenBalance, _: &[u8]) -> Weight {
+		0
+	}
 }
 
 type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type SwapOf<T> = Swap<
+	<T as system::Trait>::AccountId,
+	<T as fungible::Trait>::TokenId,
+	<T as fungible::Trait>::TokenBalance,
+	<T as system::Trait>::BlockNumber,
+>;
+pub type PoolInfoOf<T> = PoolInfo<
+	<T as system::Trait>::AccountId,
+	<T as fungible::Trait>::TokenId,
+	<T as fungible::Trait>::TokenBalance,
+>;
+pub type PositionOf<T> = Position<
+	<T as system::Trait>::AccountId,
+	<T as Trait>::SwapId,
+	<T as fungible::Trait>::TokenBalance,
+>;
+pub type FarmOf<T> = Farm<
+	<T as fungible::Trait>::TokenId,
+	<T as fungible::Trait>::TokenBalance,
+	<T as system::Trait>::BlockNumber,
+>;
+pub type StakeOf<T> = Stake<<T as fungible::Trait>::TokenBalance>;
+pub type LimitOrderOf<T> = LimitOrder<
+	<T as system::Trait>::AccountId,
+	<T as Trait>::SwapId,
+	<T as fungible::Trait>::TokenBalance,
+	<T as system::Trait>::BlockNumber,
+>;
 
 /// The swap's module id, used for deriving sovereign account IDs.
 const MODULE_ID: ModuleId = ModuleId(*b"mtg/swap");
 
+/// Derives the account that holds the permanently locked liquidity shares.
+/// Nothing can sign for it and no dispatchable spends from it.
+const LOCKED_LIQUIDITY_ID: ModuleId = ModuleId(*b"mtg/lock");
+
+/// Derives the accounts that hold the staked shares and rewards of farms.
+const FARM_ID: ModuleId = ModuleId(*b"mtg/farm");
+
+/// Derives the accounts that hold the funds of each open limit order.
+const ORDER_ID: ModuleId = ModuleId(*b"mtg/ordr");
+
+/// The maximum number of swaps a single route may pass through.
+const MAX_PATH_LENGTH: usize = 8;
+
+/// The number of price observations kept per swap for `consult`.
+const MAX_PRICE_OBSERVATIONS: usize = 64;
+
+/// The highest StableSwap amplification a swap may use.
+const MAX_AMPLIFICATION: u32 = 1_000_000;
+
+/// The factor the amplification may change by in a single ramp.
+const MAX_AMPLIFICATION_CHANGE: u32 = 10;
+
+/// The lowest weight, in parts per million, either side of a weighted swap
+/// may have.
+const MIN_WEIGHT: u32 = 20_000;
+
+/// The most steps, each up to the next initialised tick or bitmap word, a
+/// trade may take through a concentrated liquidity swap.
+const MAX_SWAP_STEPS: u32 = 64;
+
+/// The most quotes `add_liquidity_single_sided` bisects with to find how much
+/// to sell to curves without a closed form for it.
+const MAX_SINGLE_SIDED_STEPS: u32 = 32;
+
+/// The fixed point scale of the prices returned by `consult`.
+pub const PRICE_PRECISION: u128 = 1_000_000_000_000_000_000;
+
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait + fungible::Trait {
 
@@ -45,15 +440,95 @@ pub trait Trait: system::Trait + fungible::Trait {
     type SwapId: Parameter + Member + AtLeast32Bit + Default + Copy
 		+ MaybeSerializeDeserialize;
 
+	/// Identifies concentrated liquidity positions.
+	type PositionId: Parameter + Member + AtLeast32Bit + Default + Copy
+		+ MaybeSerializeDeserialize;
+
+	/// Identifies limit orders.
+	type OrderId: Parameter + Member + AtLeast32Bit + Default + Copy
+		+ MaybeSerializeDeserialize;
+
 	type Currency: Currency<Self::AccountId>;
+
+	/// Converts between the currency balance and the token balance.
+	type BalanceConvert: BalanceConvert<BalanceOf<Self>, Self::TokenBalance>;
+
+	/// Receives the funds lent out by flash swaps.
+	type FlashSwapHandler: FlashSwapHandler<Self::AccountId, Self::SwapId, Self::TokenBalance>;
+
+	/// The default fee taken from each trade for liquidity providers. It must
+	/// be less than 100% and is capped just below it otherwise.
+	type LpFee: Get<Permill>;
+
+	/// The share of the liquidity provider fee set aside for the protocol.
+	type ProtocolFee: Get<Permill>;
+
+	/// The account that receives claimed protocol fees.
+	type ProtocolFeeDestination: Get<Self::AccountId>;
+
+	/// The liquidity shares locked away forever by the first deposit into a
+	/// swap, so that its share price cannot be inflated to grief later
+	/// providers.
+	type MinimumLiquidity: Get<Self::TokenBalance>;
+
+	/// The least of either asset the first deposit into a swap may provide.
+	type MinimumDeposit: Get<Self::TokenBalance>;
+
+	/// The share of a limit order paid to the keeper that executes it.
+	type OrderTip: Get<Permill>;
+
+	/// The origin that can pause trading and adding liquidity.
+	type AdminOrigin: EnsureOrigin<Self::Origin>;
+
+	/// How far the token price of a swap may move from where it was at the
+	/// start of a `CircuitBreakerWindow` before the swap is paused. Zero
+	/// turns the circuit breaker off.
+	type CircuitBreakerThreshold: Get<Permill>;
+
+	/// The blocks price moves are measured over by the circuit breaker.
+	type CircuitBreakerWindow: Get<Self::BlockNumber>;
+
+	/// Weight information for the dispatchables of this pallet.
+	type WeightInfo: WeightInfo;
 }
 
 // Storage items for the Swap pallet.
 decl_storage! {
 	trait Store for Module<T: Trait> as SwapStorage {
-		TokenToSwap get(token_to_swap): map hasher(opaque_blake2_256) T::TokenId => T::SwapId;
-		Swaps get(swaps): map hasher(opaque_blake2_256) T::SwapId => Option<Swap<T::AccountId, T::TokenId>>;
+		Pairs get(pair_swap): map hasher(opaque_blake2_256) (Asset<T::TokenId>, Asset<T::TokenId>) => Option<T::SwapId>;
+		Swaps get(swaps): map hasher(opaque_blake2_256) T::SwapId => Option<SwapOf<T>>;
 		SwapCount get(swap_count): T::SwapId;
+		PriceObservations get(price_observations): map hasher(opaque_blake2_256) T::SwapId => Vec<PriceObservation<T::BlockNumber>>;
+		ConcentratedPools get(concentrated_pool): map hasher(opaque_blake2_256) T::SwapId => Option<ConcentratedPool>;
+		Ticks get(ticks): map hasher(opaque_blake2_256) (T::SwapId, i32) => Option<Tick>;
+		TickBitmap get(tick_bitmap): map hasher(opaque_blake2_256) (T::SwapId, i16) => U256;
+		Positions get(positions): map hasher(opaque_blake2_256) T::PositionId => Option<PositionOf<T>>;
+		PositionCount get(position_count): T::PositionId;
+		Farms get(farms): map hasher(opaque_blake2_256) T::SwapId => Option<FarmOf<T>>;
+		Stakes get(stakes): map hasher(opaque_blake2_256) (T::SwapId, T::AccountId) => Option<StakeOf<T>>;
+		Orders get(orders): map hasher(opaque_blake2_256) T::OrderId => Option<LimitOrderOf<T>>;
+		OrderCount get(order_count): T::OrderId;
+		TradingPaused get(trading_paused): bool;
+		LiquidityPaused get(liquidity_paused): bool;
+		PausedSwaps get(swap_paused): map hasher(opaque_blake2_256) T::SwapId => bool;
+		// The block a circuit breaker window started in and the token price
+		// at the time.
+		ReferencePrices get(reference_price): map hasher(opaque_blake2_256) T::SwapId => Option<(T::BlockNumber, U256)>;
+		// Swaps with a flash swap loan out.
+		LockedSwaps get(swap_locked): map hasher(opaque_blake2_256) T::SwapId => bool;
+	}
+	add_extra_genesis {
+		// Pools to create, as (asset_a, amount_a, asset_b, amount_b, owner).
+		// The reserves are minted into each swap account and the owner is
+		// given the liquidity shares. Tokens must already exist, so the
+		// fungible pallet's genesis has to be built first.
+		config(pools): Vec<(Asset<T::TokenId>, T::TokenBalance, Asset<T::TokenId>, T::TokenBalance, T::AccountId)>;
+		build(|config: &GenesisConfig<T>| {
+			for (asset_a, amount_a, asset_b, amount_b, owner) in &config.pools {
+				Module::<T>::create_genesis_pool(owner.clone(), *asset_a, *amount_a, *asset_b, *amount_b)
+					.expect("genesis pools are valid");
+			}
+		});
 	}
 }
 
@@ -62,20 +537,94 @@ decl_event!(
 	pub enum Event<T> 
 	where
 		AccountId = <T as system::Trait>::AccountId,
-		BalanceOf = BalanceOf<T>,
 		Id = <T as Trait>::SwapId,
-		TokenBalance = <T as fungible::Trait>::TokenBalance
+		PositionId = <T as Trait>::PositionId,
+		OrderId = <T as Trait>::OrderId,
+		TokenId = <T as fungible::Trait>::TokenId,
+		TokenBalance = <T as fungible::Trait>::TokenBalance,
+		BalanceOf = BalanceOf<T>,
+		BlockNumber = <T as system::Trait>::BlockNumber
 	{
 		/// Logs (SwapId, SwapAccount)
 		SwapCreated(Id, AccountId),
+		/// Logs (SwapId, fee override)
+		SwapFeeChanged(Id, Option<Permill>),
+		/// Logs (SwapId, curve)
+		SwapCurveChanged(Id, Curve<BlockNumber>),
 		/// Logs (SwapId, x, x, x)
 		LiquidityAdded(Id, AccountId, BalanceOf, TokenBalance),
 		/// Logs (SwapId, x, x, x)
 		LiquidityRemoved(Id, AccountId, BalanceOf, TokenBalance),
-		/// Logs (SwapId, buyer, currency_bought, tokens_sold, recipient)
-		CurrencyPurchase(),
-		/// Logs (SwapId, buyer, currency_sold, tokens_bought, recipient)
-		TokenPurchase(),
+		/// Logs (SwapId, buyer, tokens_sold, currency_bought, fee, recipient,
+		/// currency_reserve, token_reserve) with the fee paid in tokens and the
+		/// reserves as they are after the trade.
+		CurrencyPurchase(Id, AccountId, TokenBalance, BalanceOf, TokenBalance, AccountId, BalanceOf, TokenBalance),
+		/// Logs (SwapId, buyer, currency_sold, tokens_bought, fee, recipient,
+		/// currency_reserve, token_reserve) with the fee paid in currency and
+		/// the reserves as they are after the trade.
+		TokenPurchase(Id, AccountId, BalanceOf, TokenBalance, BalanceOf, AccountId, BalanceOf, TokenBalance),
+		/// Logs (SwapId, asset, amount) set aside for the protocol
+		ProtocolFeeAccrued(Id, Asset<TokenId>, TokenBalance),
+		/// Logs (SwapId, destination, currency, tokens)
+		ProtocolFeesClaimed(Id, AccountId, BalanceOf, TokenBalance),
+		/// Logs (SwapId, borrower, currency_lent, tokens_lent, currency_repaid,
+		/// tokens_repaid)
+		FlashSwap(Id, AccountId, BalanceOf, TokenBalance, BalanceOf, TokenBalance),
+		/// Logs (SwapId, x, base, tokens) for a swap between two tokens
+		PairLiquidityAdded(Id, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, x, base, tokens) for a swap between two tokens
+		PairLiquidityRemoved(Id, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, buyer, tokens_sold, base_bought, fee, recipient,
+		/// base_reserve, token_reserve) for a swap between two tokens, with the
+		/// fee paid in tokens and the reserves as they are after the trade.
+		PairBasePurchase(Id, AccountId, TokenBalance, TokenBalance, TokenBalance, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, buyer, base_sold, tokens_bought, fee, recipient,
+		/// base_reserve, token_reserve) for a swap between two tokens, with the
+		/// fee paid in the base asset and the reserves as they are after the
+		/// trade.
+		PairTokenPurchase(Id, AccountId, TokenBalance, TokenBalance, TokenBalance, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, destination, base, tokens) for a swap between two
+		/// tokens
+		PairProtocolFeesClaimed(Id, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, borrower, base_lent, tokens_lent, base_repaid,
+		/// tokens_repaid) for a swap between two tokens
+		PairFlashSwap(Id, AccountId, TokenBalance, TokenBalance, TokenBalance, TokenBalance),
+		/// Logs (SwapId, PositionId, owner, tick_lower, tick_upper, liquidity,
+		/// base, tokens)
+		PositionMinted(Id, PositionId, AccountId, i32, i32, u128, TokenBalance, TokenBalance),
+		/// Logs (SwapId, PositionId, owner, liquidity, base, tokens)
+		PositionBurned(Id, PositionId, AccountId, u128, TokenBalance, TokenBalance),
+		/// Logs (SwapId, PositionId, owner, base, tokens)
+		PositionFeesCollected(Id, PositionId, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, base_reserve, token_reserve)
+		ReservesSynced(Id, TokenBalance, TokenBalance),
+		/// Logs (SwapId, recipient, base, tokens)
+		Skimmed(Id, AccountId, TokenBalance, TokenBalance),
+		/// Logs (SwapId, reward_token, reward_per_block, start, end)
+		FarmCreated(Id, TokenId, TokenBalance, BlockNumber, BlockNumber),
+		/// Logs (SwapId, funder, rewards)
+		FarmFunded(Id, AccountId, TokenBalance),
+		/// Logs (SwapId, staker, shares)
+		Staked(Id, AccountId, TokenBalance),
+		/// Logs (SwapId, staker, shares)
+		Unstaked(Id, AccountId, TokenBalance),
+		/// Logs (SwapId, staker, rewards)
+		RewardsHarvested(Id, AccountId, TokenBalance),
+		/// Logs (OrderId, SwapId, owner, side sold, amount, limit_price, expiry)
+		OrderPlaced(OrderId, Id, AccountId, Side, TokenBalance, u128, BlockNumber),
+		/// Logs (OrderId, keeper, sold, bought, tip)
+		OrderExecuted(OrderId, AccountId, TokenBalance, TokenBalance, TokenBalance),
+		/// Logs (OrderId)
+		OrderCancelled(OrderId),
+		/// Logs (paused)
+		TradingPauseChanged(bool),
+		/// Logs (paused)
+		LiquidityPauseChanged(bool),
+		/// Logs (SwapId, paused)
+		SwapPauseChanged(Id, bool),
+		/// Logs (SwapId) when a trade trips the circuit breaker and pauses
+		/// the swap.
+		CircuitBreakerTripped(Id),
 	}
 );
 
@@ -90,12 +639,16 @@ decl_error! {
 		ZeroAmount,
 		/// No Swap exists at this Id.
 		NoSwapExists,
-		/// A Swap already exists for a particular TokenId.
+		/// A Swap already exists for a particular pair of assets.
 		SwapAlreadyExists,
 		/// Requested zero liquidity.
 		RequestedZeroLiquidity,
 		/// Would add too many tokens to liquidity.
 		TooManyTokens,
+		/// The first deposit into a swap is below the minimum.
+		DepositTooSmall,
+		/// Would add too much of the base asset to liquidity.
+		TooMuchBase,
 		/// Not enough liquidity created.
 		TooLowLiquidity,
 		/// No currency is being swapped.
@@ -114,6 +667,103 @@ decl_error! {
 		TooExpensiveCurrency,
 		/// Swap would cost too much in tokens.
 		TooExpensiveTokens,
+		/// Trying to swap tokens through the same swap twice.
+		IdenticalSwaps,
+		/// The path is empty, repeats a swap or does not connect the assets.
+		InvalidPath,
+		/// The path passes through too many swaps.
+		PathTooLong,
+		/// The fee must be less than 100%.
+		InvalidFee,
+		/// The swap has no protocol fees to claim.
+		NoProtocolFees,
+		/// The swap does not hold enough of the requested asset.
+		InsufficientReserve,
+		/// The recipient would hold less currency than the existential
+		/// deposit.
+		BelowExistentialDeposit,
+		/// The caller cannot pay the amount and keep its account alive.
+		InsufficientBalance,
+		/// An amount does not fit in the balance type.
+		Overflow,
+		/// The swap has no price observation old enough for the window.
+		InsufficientHistory,
+		/// The flash swap was not paid back with its fee.
+		FlashSwapNotRepaid,
+		/// The swap has a flash swap loan out.
+		SwapLocked,
+		/// A swap must trade two different assets.
+		IdenticalAssets,
+		/// The swap does not trade this asset.
+		AssetNotInSwap,
+		/// The swap does not trade tokens against the native currency.
+		NotCurrencySwap,
+		/// The curve of a swap can only be changed before it has liquidity.
+		SwapHasLiquidity,
+		/// The swap does not use the StableSwap curve.
+		NotStableSwap,
+		/// The amplification or its ramp is out of bounds.
+		InvalidAmplification,
+		/// The StableSwap invariant could not be computed for the reserves.
+		InvariantNotFound,
+		/// The weights of a weighted swap are out of bounds.
+		InvalidWeight,
+		/// The trade is too large a share of the reserves of a weighted swap.
+		TradeTooLarge,
+		/// The swap uses concentrated liquidity positions instead of shares.
+		ConcentratedSwap,
+		/// The swap does not use concentrated liquidity.
+		NotConcentratedSwap,
+		/// The tick spacing must be between 1 and `MAX_TICK_SPACING`.
+		InvalidTickSpacing,
+		/// The price is outside of the range of ticks.
+		InvalidPrice,
+		/// The ticks of a position must be ordered multiples of the tick
+		/// spacing within the range of ticks.
+		InvalidTickRange,
+		/// The swap or position does not have enough liquidity.
+		InsufficientLiquidity,
+		/// The trade crosses too many ticks of a concentrated liquidity swap.
+		TooManySwapSteps,
+		/// No position exists at this Id.
+		NoPositionExists,
+		/// The position belongs to another account.
+		NotPositionOwner,
+		/// The position has no fees to collect.
+		NoFeesOwed,
+		/// No farm exists for this swap.
+		NoFarmExists,
+		/// A farm already exists for this swap.
+		FarmAlreadyExists,
+		/// The farm must end after it starts and after the current block.
+		InvalidFarmSchedule,
+		/// The reward token does not exist or is the swap's own shares.
+		InvalidRewardToken,
+		/// Trying to unstake more shares than are staked.
+		NotEnoughStaked,
+		/// The stake has no rewards to harvest.
+		NoRewardsOwed,
+		/// The farm holds none of the rewards owed.
+		FarmUnderfunded,
+		/// No order exists at this Id.
+		NoOrderExists,
+		/// The order can no longer be executed.
+		OrderExpired,
+		/// The swap does not yet pay the limit price of the order.
+		LimitPriceNotReached,
+		/// The limit price of an order must be above zero.
+		InvalidLimitPrice,
+		/// Only the owner can cancel an order before it expires.
+		NotOrderOwner,
+		/// Trading is paused.
+		TradingPaused,
+		/// Adding liquidity is paused.
+		LiquidityPaused,
+		/// The swap is paused.
+		SwapPaused,
+		/// The trade would move the price further than the circuit breaker
+		/// allows, so the swap has been paused.
+		CircuitBreakerTripped,
 	}
 }
 
@@ -126,39 +776,169 @@ decl_module! {
 
 		fn deposit_event() = default;
 		
+		/// Creates a swap trading `token_id` against the native currency.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::create_swap())]
 		pub fn create_swap(origin,
 			token_id: T::TokenId,
 		) -> dispatch::DispatchResult
 		{
 			let sender = ensure_signed(origin)?;
-			ensure!(!TokenToSwap::<T>::contains_key(token_id), Error::<T>::SwapAlreadyExists);
+			Self::do_create_swap(sender, Asset::Currency, Asset::Token(token_id))
+		}
+
+		/// Creates a swap trading two different assets, either of which may be
+		/// the native currency.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::create_swap())]
+		pub fn create_pair(origin,
+			asset_a: Asset<T::TokenId>,
+			asset_b: Asset<T::TokenId>,
+		) -> dispatch::DispatchResult
+		{
+			let sender = ensure_signed(origin)?;
+			Self::do_create_swap(sender, asset_a, asset_b)
+		}
+
+		/// Overrides the liquidity provider fee of a swap, or restores the
+		/// default `LpFee` when `fee` is `None`.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_swap_fee())]
+		pub fn set_swap_fee(origin,
+			swap_id: T::SwapId,
+			fee: Option<Permill>,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
+			ensure!(fee.map_or(true, |fee| fee < Permill::one()), Error::<T>::InvalidFee);
+
+			let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			swap.fee = fee;
+			<Swaps<T>>::insert(swap_id, swap);
 
-			let swap_id = Self::swap_count();
-			let next_id = swap_id.checked_add(&One::one())
-				.ok_or("Overflow")?;
+			Self::deposit_event(RawEvent::SwapFeeChanged(swap_id, fee));
 
-			let swap_token_id = fungible::Module::<T>::create_token(sender, Zero::zero());
+			Ok(())
+		}
 
-			let account: T::AccountId = MODULE_ID.into_sub_account(swap_token_id);
+		/// Switches a swap without liquidity to the StableSwap curve with the
+		/// given amplification, or back to constant product when it is `None`.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_swap_curve())]
+		pub fn set_swap_curve(origin,
+			swap_id: T::SwapId,
+			amplification: Option<u32>,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
 
-			let new_swap = Swap {
-				token_id: token_id,
-				swap_token: swap_token_id,
-				account: account.clone(),
+			let now = system::Module::<T>::block_number();
+			let curve = match amplification {
+				None => Curve::ConstantProduct,
+				Some(a) => {
+					ensure!(a > 0 && a <= MAX_AMPLIFICATION, Error::<T>::InvalidAmplification);
+					Curve::StableSwap { initial_a: a, future_a: a, ramp_start: now, ramp_end: now }
+				},
 			};
 
-			<TokenToSwap<T>>::insert(token_id, swap_id);
-			<Swaps<T>>::insert(swap_id, new_swap);
-			<SwapCount<T>>::put(next_id);
+			Self::do_set_curve(swap_id, curve)
+		}
+
+		/// Switches a swap without liquidity to a weighted pool holding
+		/// `base_weight` of its value in the base asset and the rest in tokens.
+		///
+		/// Each side must have a weight of at least `MIN_WEIGHT`.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_swap_curve())]
+		pub fn set_swap_weights(origin,
+			swap_id: T::SwapId,
+			base_weight: Permill,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
+
+			let base_weight_parts = base_weight.deconstruct();
+			ensure!(
+				base_weight_parts >= MIN_WEIGHT
+					&& base_weight_parts <= Permill::one().deconstruct() - MIN_WEIGHT,
+				Error::<T>::InvalidWeight
+			);
+
+			Self::do_set_curve(swap_id, Curve::Weighted { base_weight })
+		}
+
+		/// Ramps the amplification of a StableSwap swap linearly from its
+		/// current value to `future_a` at block `ramp_end`.
+		///
+		/// The amplification may change by at most `MAX_AMPLIFICATION_CHANGE`
+		/// times in a single ramp.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::ramp_amplification())]
+		pub fn ramp_amplification(origin,
+			swap_id: T::SwapId,
+			future_a: u32,
+			ramp_end: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
+
+			let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let initial_a = Self::amplification(&swap).ok_or(Error::<T>::NotStableSwap)?;
+
+			let now = system::Module::<T>::block_number();
+			ensure!(ramp_end > now, Error::<T>::InvalidAmplification);
+			ensure!(future_a > 0 && future_a <= MAX_AMPLIFICATION, Error::<T>::InvalidAmplification);
+			ensure!(
+				future_a <= initial_a.saturating_mul(MAX_AMPLIFICATION_CHANGE)
+					&& initial_a <= future_a.saturating_mul(MAX_AMPLIFICATION_CHANGE),
+				Error::<T>::InvalidAmplification
+			);
+
+			swap.curve = Curve::StableSwap { initial_a, future_a, ramp_start: now, ramp_end };
+			let curve = swap.curve;
+			<Swaps<T>>::insert(swap_id, swap);
+
+			Self::deposit_event(RawEvent::SwapCurveChanged(swap_id, curve));
+
+			Ok(())
+		}
+
+		/// Sends the protocol fees accrued by a swap to `ProtocolFeeDestination`.
+		///
+		/// Anyone may trigger the claim.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::claim_protocol_fees())]
+		pub fn claim_protocol_fees(origin,
+			swap_id: T::SwapId,
+		) -> dispatch::DispatchResult
+		{
+			ensure_signed(origin)?;
+
+			Self::ensure_unlocked(swap_id)?;
+			let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let base = swap.protocol_base;
+			let tokens = swap.protocol_tokens;
+			ensure!(!base.is_zero() || !tokens.is_zero(), Error::<T>::NoProtocolFees);
+
+			let destination = T::ProtocolFeeDestination::get();
+			if !base.is_zero() {
+				Self::transfer_asset(swap.base, &swap.account, &destination, base, ExistenceRequirement::AllowDeath)?;
+			}
+			if !tokens.is_zero() {
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), destination.clone(), tokens)?;
+			}
+
+			let event = if swap.base == Asset::Currency {
+				RawEvent::ProtocolFeesClaimed(swap_id, destination, Self::currency_amount(base), tokens)
+			} else {
+				RawEvent::PairProtocolFeesClaimed(swap_id, destination, base, tokens)
+			};
+			swap.protocol_base = Zero::zero();
+			swap.protocol_tokens = Zero::zero();
+			<Swaps<T>>::insert(swap_id, swap);
 
-			Self::deposit_event(RawEvent::SwapCreated(swap_id, account));
+			Self::deposit_event(event);
 
 			Ok(())
 		}
         
+        #[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::add_liquidity())]
         pub fn add_liquidity(origin,
 			swap_id: T::SwapId,				// ID of swap to access.
-			currency_amount: BalanceOf<T>,  // Amount of base currency to lock.
+			base_amount: T::TokenBalance,	// Amount of the base asset to lock.
             min_liquidity: T::TokenBalance,	// Min amount of swap shares to create.
 			max_tokens: T::TokenBalance,	// Max amount of tokens to input.
             deadline: T::BlockNumber,		// When to invalidate the transaction.
@@ -171,34 +951,50 @@ decl_module! {
 			let who = ensure_signed(origin.clone())?;
 
 			ensure!(max_tokens > Zero::zero(), Error::<T>::ZeroTokens);
-			ensure!(currency_amount > Zero::zero(), Error::<T>::ZeroAmount);
+			ensure!(base_amount > Zero::zero(), Error::<T>::ZeroAmount);
 
 			if let Some(swap) = Self::swaps(swap_id) {
+				ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+				Self::ensure_adding_liquidity_allowed(swap_id)?;
 				let total_liquidity = fungible::Module::<T>::total_supply(swap.swap_token.clone());
 
 				if total_liquidity > Zero::zero() {
 					ensure!(min_liquidity > Zero::zero(), Error::<T>::RequestedZeroLiquidity);
-					let swap_balance = Self::convert(Self::get_swap_balance(&swap));
+					let base_reserve = Self::get_base_reserve(&swap);
 					let token_reserve = Self::get_token_reserve(&swap);
-					let token_amount = Self::convert(currency_amount) * token_reserve / swap_balance;
-					let liquidity_minted = Self::convert(currency_amount) * total_liquidity / swap_balance;
+					let token_amount = Self::mul_div(base_amount, token_reserve, base_reserve)?;
+					let liquidity_minted = Self::mul_div(base_amount, total_liquidity, base_reserve)?;
 
 					ensure!(max_tokens >= token_amount, Error::<T>::TooManyTokens);
 					ensure!(liquidity_minted >= min_liquidity, Error::<T>::TooLowLiquidity);
 
-					T::Currency::transfer(&who, &swap.account, currency_amount, ExistenceRequirement::KeepAlive)?;
+					Self::update_price_oracle(swap_id)?;
+					Self::transfer_asset(swap.base, &who, &swap.account, base_amount, ExistenceRequirement::KeepAlive)?;
 					fungible::Module::<T>::mint(swap.swap_token.clone(), who.clone(), liquidity_minted)?;
-					fungible::Module::<T>::do_transfer(swap.token_id, who.clone(), swap.account, token_amount)?;
-					Self::deposit_event(RawEvent::LiquidityAdded(swap_id, who.clone(), currency_amount.clone(), token_amount));
+					fungible::Module::<T>::do_transfer(swap.token_id, who.clone(), swap.account.clone(), token_amount)?;
+					Self::update_reserves(swap_id, base_amount, token_amount, Zero::zero(), Zero::zero())?;
+					Self::deposit_liquidity_added(swap_id, &swap, who.clone(), base_amount, token_amount);
 				} else {
 					// Fresh swap with no liquidity ~
 					let token_amount = max_tokens;
+					let minimum_deposit = T::MinimumDeposit::get();
+					ensure!(
+						base_amount >= minimum_deposit && token_amount >= minimum_deposit,
+						Error::<T>::DepositTooSmall
+					);
 					let this = swap.account.clone();
-					T::Currency::transfer(&who, &swap.account, currency_amount, ExistenceRequirement::KeepAlive)?;
-					let initial_liquidity: u64 = T::Currency::free_balance(&this).saturated_into::<u64>();
-					fungible::Module::<T>::mint(swap.swap_token.clone(), who.clone(), initial_liquidity.saturated_into())?;
+					let initial_liquidity = swap.base_reserve
+						.checked_add(&base_amount)
+						.ok_or(Error::<T>::Overflow)?;
+					let minimum_liquidity = T::MinimumLiquidity::get();
+					ensure!(initial_liquidity > minimum_liquidity, Error::<T>::DepositTooSmall);
+					Self::update_price_oracle(swap_id)?;
+					Self::transfer_asset(swap.base, &who, &swap.account, base_amount, ExistenceRequirement::KeepAlive)?;
+					Self::lock_minimum_liquidity(&swap)?;
+					fungible::Module::<T>::mint(swap.swap_token.clone(), who.clone(), initial_liquidity - minimum_liquidity)?;
 					fungible::Module::<T>::do_transfer(swap.token_id, who.clone(), this.clone(), token_amount)?;
-					Self::deposit_event(RawEvent::LiquidityAdded(swap_id, who, currency_amount, token_amount));
+					Self::update_reserves(swap_id, base_amount, token_amount, Zero::zero(), Zero::zero())?;
+					Self::deposit_liquidity_added(swap_id, &swap, who, base_amount, token_amount);
 				}
 
 				Ok(())
@@ -206,11 +1002,82 @@ decl_module! {
 				Err(Error::<T>::NoSwapExists)?
 			}
 		}
+
+		/// Adds liquidity to a swap with `amount` of only one of its assets.
+		///
+		/// Part of `amount` is first sold to the swap, as much as leaves the
+		/// rest in proportion to what it bought at the reserves after the sale.
+		/// Both sides are then deposited and any rounding dust of either asset
+		/// stays with the caller.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::add_liquidity_single_sided())]
+		pub fn add_liquidity_single_sided(origin,
+			swap_id: T::SwapId,
+			asset_in: Asset<T::TokenId>,
+			amount: T::TokenBalance,
+			min_shares: T::TokenBalance,
+			deadline: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline > now, Error::<T>::Deadline);
+
+			let who = ensure_signed(origin)?;
+
+			ensure!(amount > Zero::zero(), Error::<T>::ZeroAmount);
+			ensure!(min_shares > Zero::zero(), Error::<T>::RequestedZeroLiquidity);
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			ensure!(
+				asset_in == swap.base || asset_in == Asset::Token(swap.token_id),
+				Error::<T>::AssetNotInSwap
+			);
+			Self::ensure_adding_liquidity_allowed(swap_id)?;
+			Self::ensure_trading_allowed(swap_id)?;
+			let total_liquidity = fungible::Module::<T>::total_supply(swap.swap_token);
+			ensure!(total_liquidity > Zero::zero(), Error::<T>::NoLiquidity);
+
+			let (asset_out, input_reserve, output_reserve) = Self::hop_side(&swap, asset_in)?;
+			let sold = Self::single_sided_sale(swap_id, &swap, asset_in, amount, input_reserve, output_reserve)?;
+			let bought = Self::swap_input_price(swap_id, &swap, asset_in, sold, input_reserve, output_reserve)?;
+
+			// The deposit is priced at the reserves the sale leaves behind.
+			let (_, after, _) = Self::simulate_trade(swap_id, &swap, asset_in, sold, bought, true)?;
+			let (base_reserve, token_reserve) = (after.base_reserve, after.token_reserve);
+			let (held_base, held_tokens) = if asset_in == swap.base {
+				(amount - sold, bought)
+			} else {
+				(bought, amount - sold)
+			};
+			let base_amount = held_base.min(Self::mul_div(held_tokens, base_reserve, token_reserve)?);
+			let token_amount = Self::mul_div(base_amount, token_reserve, base_reserve)?;
+			let liquidity_minted = Self::mul_div(base_amount, total_liquidity, base_reserve)?;
+			ensure!(liquidity_minted >= min_shares, Error::<T>::TooLowLiquidity);
+			Self::ensure_within_circuit_breaker(swap_id, &swap, asset_in, sold, bought, true)?;
+			// Nothing is rolled back if a transfer fails, so everything the
+			// caller pays in is checked before the sale.
+			Self::ensure_can_pay_in(asset_in, &who, amount)?;
+
+			Self::update_price_oracle(swap_id)?;
+			Self::transfer_asset(asset_in, &who, &swap.account, sold, ExistenceRequirement::KeepAlive)?;
+			Self::apply_trade(swap_id, &swap, asset_in, sold, bought, true)?;
+			Self::transfer_asset(asset_out, &swap.account, &who, bought, ExistenceRequirement::AllowDeath)?;
+			Self::record_trade(swap_id, &swap, &who, &who, asset_in, sold, bought);
+
+			Self::transfer_asset(swap.base, &who, &swap.account, base_amount, ExistenceRequirement::KeepAlive)?;
+			fungible::Module::<T>::do_transfer(swap.token_id, who.clone(), swap.account.clone(), token_amount)?;
+			fungible::Module::<T>::mint(swap.swap_token, who.clone(), liquidity_minted)?;
+			Self::update_reserves(swap_id, base_amount, token_amount, Zero::zero(), Zero::zero())?;
+			Self::deposit_liquidity_added(swap_id, &swap, who, base_amount, token_amount);
+
+			Ok(())
+		}
 		
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::remove_liquidity())]
 		pub fn remove_liquidity(origin,
 			swap_id: T::SwapId,
 			shares_to_burn: T::TokenBalance, 
-			min_currency: BalanceOf<T>,		// Minimum currency to withdraw.
+			min_base: T::TokenBalance,		// Minimum base asset to withdraw.
 			min_tokens: T::TokenBalance,	// Minimum tokens to withdraw.
 			deadline: T::BlockNumber,
 		) -> dispatch::DispatchResult
@@ -223,25 +1090,22 @@ decl_module! {
 			ensure!(shares_to_burn > Zero::zero(), Error::<T>::BurnZeroShares);
 
 			if let Some(swap) = Self::swaps(swap_id) {
-				let total_liquidity = fungible::Module::<T>::total_supply(swap.swap_token.clone());
-
-				ensure!(total_liquidity > Zero::zero(), Error::<T>::NoLiquidity);
+				ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+				Self::ensure_unlocked(swap_id)?;
+				let (base_amount, token_amount) = Self::liquidity_value(&swap, shares_to_burn)?;
 
-				let token_reserve = Self::get_token_reserve(&swap);
-				let swap_balance = Self::get_swap_balance(&swap);
-				let currency_amount = shares_to_burn.clone() * Self::convert(swap_balance) / total_liquidity.clone();
-				let token_amount = shares_to_burn.clone() * token_reserve / total_liquidity.clone();
-
-				ensure!(Self::unconvert(currency_amount) >= min_currency, Error::<T>::NotEnoughCurrency);
+				ensure!(base_amount >= min_base, Error::<T>::NotEnoughCurrency);
 				ensure!(token_amount >= min_tokens, Error::<T>::NotEnoughTokens);
 
+				Self::update_price_oracle(swap_id)?;
 				fungible::Module::<T>::burn(swap.swap_token.clone(), who.clone(), shares_to_burn)?;
 
-				T::Currency::transfer(&swap.account, &who, Self::unconvert(currency_amount), ExistenceRequirement::AllowDeath)?;
+				Self::transfer_asset(swap.base, &swap.account, &who, base_amount, ExistenceRequirement::AllowDeath)?;
 				// Need to ensure this happens.
 				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), who.clone(), token_amount.clone())?;
+				Self::update_reserves(swap_id, Zero::zero(), Zero::zero(), base_amount, token_amount)?;
 				
-				Self::deposit_event(RawEvent::LiquidityRemoved(swap_id, who, Self::unconvert(currency_amount), token_amount));
+				Self::deposit_liquidity_removed(swap_id, &swap, who, base_amount, token_amount);
 
 				Ok(())
 			} else {
@@ -249,10 +1113,75 @@ decl_module! {
 			}
 		}
 
+		/// Burns `shares_to_burn` of a swap and sells the share of one side to
+		/// the swap for the other, so that only the `want` side is withdrawn.
+		///
+		/// The sale is priced at the reserves left after the withdrawal and at
+		/// least `min_out` must be returned in total.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::remove_liquidity_one_side())]
+		pub fn remove_liquidity_one_side(origin,
+			swap_id: T::SwapId,
+			shares_to_burn: T::TokenBalance,
+			want: Side,
+			min_out: T::TokenBalance,
+			deadline: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline > now, Error::<T>::Deadline);
+
+			let who = ensure_signed(origin)?;
+
+			ensure!(shares_to_burn > Zero::zero(), Error::<T>::BurnZeroShares);
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_trading_allowed(swap_id)?;
+			let (base_amount, token_amount) = Self::liquidity_value(&swap, shares_to_burn)?;
+			let base_reserve = Self::get_base_reserve(&swap)
+				.checked_sub(&base_amount)
+				.ok_or(Error::<T>::InsufficientReserve)?;
+			let token_reserve = Self::get_token_reserve(&swap)
+				.checked_sub(&token_amount)
+				.ok_or(Error::<T>::InsufficientReserve)?;
+
+			let token = Asset::Token(swap.token_id);
+			let (asset_in, asset_out, sold, kept, input_reserve, output_reserve) = match want {
+				Side::Base => (token, swap.base, token_amount, base_amount, token_reserve, base_reserve),
+				Side::Token => (swap.base, token, base_amount, token_amount, base_reserve, token_reserve),
+			};
+			let bought = Self::swap_input_price(swap_id, &swap, asset_in, sold, input_reserve, output_reserve)?;
+			let amount_out = kept.checked_add(&bought).ok_or(Error::<T>::Overflow)?;
+			match want {
+				Side::Base => ensure!(amount_out >= min_out, Error::<T>::NotEnoughCurrency),
+				Side::Token => ensure!(amount_out >= min_out, Error::<T>::NotEnoughTokens),
+			}
+			let mut remaining = swap.clone();
+			remaining.base_reserve = base_reserve;
+			remaining.token_reserve = token_reserve;
+			Self::ensure_within_circuit_breaker(swap_id, &remaining, asset_in, sold, bought, true)?;
+			Self::ensure_can_pay_out(asset_out, &swap.account, &who, amount_out)?;
+
+			Self::update_price_oracle(swap_id)?;
+			fungible::Module::<T>::burn(swap.swap_token, who.clone(), shares_to_burn)?;
+			Self::update_reserves(swap_id, Zero::zero(), Zero::zero(), base_amount, token_amount)?;
+			Self::deposit_liquidity_removed(swap_id, &swap, who.clone(), base_amount, token_amount);
+
+			// The side being sold never leaves the swap account.
+			if !sold.is_zero() {
+				Self::apply_trade(swap_id, &swap, asset_in, sold, bought, true)?;
+				Self::record_trade(swap_id, &swap, &who, &who, asset_in, sold, bought);
+			}
+			Self::transfer_asset(asset_out, &swap.account, &who, amount_out, ExistenceRequirement::AllowDeath)?;
+
+			Ok(())
+		}
+
 		/// Converts currency to tokens.
 		///
 		/// User specifies the exact amount of currency to spend and the minimum
 		/// tokens to be returned.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::currency_to_tokens_input())]
 		pub fn currency_to_tokens_input(origin,
 			swap_id: T::SwapId,
 			currency: BalanceOf<T>,
@@ -270,16 +1199,18 @@ decl_module! {
 			ensure!(min_tokens > Zero::zero(), Error::<T>::NoTokensSwapped);
 
 			if let Some(swap) = Self::swaps(swap_id) {
-				let token_reserve = Self::get_token_reserve(&swap);
-				let swap_balance = Self::get_swap_balance(&swap);
-				let tokens_bought = Self::get_input_price(Self::convert(currency), Self::convert(swap_balance), token_reserve);
+				Self::ensure_trading_allowed(swap_id)?;
+				let tokens_bought = Self::currency_to_token_input_price(swap_id, &swap, currency)?;
 				
 				ensure!(tokens_bought >= min_tokens, Error::<T>::NotEnoughTokens);
+				Self::ensure_within_circuit_breaker(swap_id, &swap, Asset::Currency, Self::convert(currency)?, tokens_bought, true)?;
 				
+				Self::update_price_oracle(swap_id)?;
 				T::Currency::transfer(&buyer, &swap.account, currency, ExistenceRequirement::KeepAlive)?;
-				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), recipient, tokens_bought)?;
+				Self::apply_trade(swap_id, &swap, Asset::Currency, Self::convert(currency)?, tokens_bought, true)?;
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), recipient.clone(), tokens_bought)?;
 
-				Self::deposit_event(RawEvent::TokenPurchase());
+				Self::record_trade(swap_id, &swap, &buyer, &recipient, Asset::Currency, Self::convert(currency)?, tokens_bought);
 
 				Ok(())
 			} else {
@@ -291,6 +1222,7 @@ decl_module! {
 		///
 		/// User specifies the maximum currency to spend and the exact amount of
 		/// tokens to be returned.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::currency_to_tokens_output())]
 		pub fn currency_to_tokens_output(origin,
 			swap_id: T::SwapId,
 			tokens_bought: T::TokenBalance,
@@ -308,16 +1240,18 @@ decl_module! {
 			ensure!(max_currency > Zero::zero(), Error::<T>::NoCurrencySwapped);
 
 			if let Some(swap) = Self::swaps(swap_id) {
-				let token_reserve = Self::get_token_reserve(&swap);
-				let swap_balance = Self::get_swap_balance(&swap);
-				let currency_sold = Self::get_output_price(tokens_bought, Self::convert(swap_balance), token_reserve);
+				Self::ensure_trading_allowed(swap_id)?;
+				let currency_sold = Self::currency_to_token_output_price(swap_id, &swap, tokens_bought)?;
 
-				ensure!(Self::unconvert(currency_sold) <= max_currency, Error::<T>::TooExpensiveCurrency);
+				ensure!(currency_sold <= max_currency, Error::<T>::TooExpensiveCurrency);
+				Self::ensure_within_circuit_breaker(swap_id, &swap, Asset::Currency, Self::convert(currency_sold)?, tokens_bought, false)?;
 
-				T::Currency::transfer(&buyer, &swap.account, Self::unconvert(currency_sold), ExistenceRequirement::KeepAlive)?;
-				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), recipient, tokens_bought)?;
+				Self::update_price_oracle(swap_id)?;
+				T::Currency::transfer(&buyer, &swap.account, currency_sold, ExistenceRequirement::KeepAlive)?;
+				Self::apply_trade(swap_id, &swap, Asset::Currency, Self::convert(currency_sold)?, tokens_bought, false)?;
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), recipient.clone(), tokens_bought)?;
 				
-				Self::deposit_event(RawEvent::TokenPurchase());
+				Self::record_trade(swap_id, &swap, &buyer, &recipient, Asset::Currency, Self::convert(currency_sold)?, tokens_bought);
 
 				Ok(())
 			} else {
@@ -329,6 +1263,7 @@ decl_module! {
 		///
 		/// The user specifies exact amount of tokens sold and minimum amount of
 		/// currency that is returned.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::tokens_to_currency_input())]
 		pub fn tokens_to_currency_input(origin,
 			swap_id: T::SwapId,
 			tokens_sold: T::TokenBalance,
@@ -346,16 +1281,19 @@ decl_module! {
 			ensure!(min_currency > Zero::zero(), Error::<T>::NoCurrencySwapped);
 
 			if let Some(swap) = Self::swaps(swap_id) {
-				let token_reserve = Self::get_token_reserve(&swap);
-				let swap_balance = Self::get_swap_balance(&swap);
-				let currency_bought = Self::get_input_price(tokens_sold, token_reserve, Self::convert(swap_balance));
+				Self::ensure_trading_allowed(swap_id)?;
+				let currency_bought = Self::token_to_currency_input_price(swap_id, &swap, tokens_sold)?;
 
-				ensure!(currency_bought >= Self::convert(min_currency), Error::<T>::NotEnoughCurrency);
+				ensure!(currency_bought >= min_currency, Error::<T>::NotEnoughCurrency);
+				let token = Asset::Token(swap.token_id);
+				Self::ensure_within_circuit_breaker(swap_id, &swap, token, tokens_sold, Self::convert(currency_bought)?, true)?;
 
-				T::Currency::transfer(&swap.account, &recipient, Self::unconvert(currency_bought), ExistenceRequirement::AllowDeath)?;
-				fungible::Module::<T>::do_transfer(swap.token_id, buyer, swap.account, tokens_sold)?;
+				Self::update_price_oracle(swap_id)?;
+				fungible::Module::<T>::do_transfer(swap.token_id, buyer.clone(), swap.account.clone(), tokens_sold)?;
+				Self::apply_trade(swap_id, &swap, token, tokens_sold, Self::convert(currency_bought)?, true)?;
+				T::Currency::transfer(&swap.account, &recipient, currency_bought, ExistenceRequirement::AllowDeath)?;
 				
-				Self::deposit_event(RawEvent::CurrencyPurchase());
+				Self::record_trade(swap_id, &swap, &buyer, &recipient, Asset::Token(swap.token_id), tokens_sold, Self::convert(currency_bought)?);
 
 				Ok(())
 			} else {
@@ -367,6 +1305,7 @@ decl_module! {
 		///
 		/// The user specifies the maximum tokens to swap and the exact
 		/// currency to be returned.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::tokens_to_currency_output())]
 		pub fn tokens_to_currency_output(origin,
 			swap_id:  T::SwapId,
 			currency_bought: BalanceOf<T>,
@@ -384,92 +1323,2780 @@ decl_module! {
 			ensure!(currency_bought > Zero::zero(), Error::<T>::NoCurrencySwapped);
 
 			if let Some(swap) = Self::swaps(swap_id) {
-				let token_reserve = Self::get_token_reserve(&swap);
-				let swap_balance = Self::get_swap_balance(&swap);
-				let tokens_sold = Self::get_output_price(Self::convert(currency_bought), token_reserve, Self::convert(swap_balance));
+				Self::ensure_trading_allowed(swap_id)?;
+				let tokens_sold = Self::token_to_currency_output_price(swap_id, &swap, currency_bought)?;
 
 				ensure!(max_tokens >= tokens_sold, Error::<T>::TooExpensiveTokens);
+				let token = Asset::Token(swap.token_id);
+				Self::ensure_within_circuit_breaker(swap_id, &swap, token, tokens_sold, Self::convert(currency_bought)?, false)?;
 
-				T::Currency::transfer(&swap.account, &buyer, currency_bought, ExistenceRequirement::AllowDeath)?;
-				fungible::Module::<T>::do_transfer(swap.token_id, recipient, swap.account, tokens_sold)?;
+				Self::update_price_oracle(swap_id)?;
+				// The buyer pays the tokens and the recipient receives the currency.
+				fungible::Module::<T>::do_transfer(swap.token_id, buyer.clone(), swap.account.clone(), tokens_sold)?;
+				Self::apply_trade(swap_id, &swap, token, tokens_sold, Self::convert(currency_bought)?, false)?;
+				T::Currency::transfer(&swap.account, &recipient, currency_bought, ExistenceRequirement::AllowDeath)?;
 				
-				Self::deposit_event(RawEvent::CurrencyPurchase());
+				Self::record_trade(swap_id, &swap, &buyer, &recipient, Asset::Token(swap.token_id), tokens_sold, Self::convert(currency_bought)?);
 
 				Ok(())
 			} else {
 				Err(Error::<T>::NoSwapExists)?
 			}
 		}
-	}
-}
 
-impl<T: Trait> Module<T> {
-	pub fn get_currency_to_token_input_price(swap: &Swap<T::AccountId, T::TokenId>, currency_sold: BalanceOf<T>)
-		-> T::TokenBalance
-	{
-		if currency_sold == Zero::zero() { return Zero::zero(); }
+		/// Converts tokens to tokens of another kind.
+		///
+		/// The tokens of `swap_id` are sold for currency which is used to buy
+		/// `token_id` from its own swap. The user specifies the exact amount of
+		/// tokens sold and the minimum amount of `token_id` to be returned.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::swap_exact_in_along_path(2))]
+		pub fn tokens_to_tokens_input(origin,
+			swap_id: T::SwapId,
+			tokens_sold: T::TokenBalance,
+			min_tokens_bought: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+			token_id: T::TokenId,
+		) -> dispatch::DispatchResult
+		{
+			let buyer = ensure_signed(origin)?;
 
-		let token_reserve = Self::get_token_reserve(swap);
-		let swap_balance = Self::get_swap_balance(swap);
-		Self::get_input_price(Self::convert(currency_sold), Self::convert(swap_balance), token_reserve)
-	}
+			let target_id = Self::get_swap_by_token(token_id).ok_or(Error::<T>::NoSwapExists)?;
+
+			Self::do_tokens_to_swap_input(buyer, swap_id, tokens_sold, min_tokens_bought, deadline, recipient, target_id)
+		}
+
+		/// Converts tokens to tokens of another kind.
+		///
+		/// The user specifies the exact amount of `token_id` to be returned and
+		/// the maximum amount of tokens of `swap_id` to sell for it.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::swap_exact_out_along_path(2))]
+		pub fn tokens_to_tokens_output(origin,
+			swap_id: T::SwapId,
+			tokens_bought: T::TokenBalance,
+			max_tokens_sold: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+			token_id: T::TokenId,
+		) -> dispatch::DispatchResult
+		{
+			let buyer = ensure_signed(origin)?;
+
+			let target_id = Self::get_swap_by_token(token_id).ok_or(Error::<T>::NoSwapExists)?;
+
+			Self::do_tokens_to_swap_output(buyer, swap_id, tokens_bought, max_tokens_sold, deadline, recipient, target_id)
+		}
+
+		/// Converts tokens to the tokens of the swap at `target_id`.
+		///
+		/// Same as `tokens_to_tokens_input` but the bought tokens are selected
+		/// by their swap instead of their token id.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::swap_exact_in_along_path(2))]
+		pub fn tokens_to_swap_input(origin,
+			swap_id: T::SwapId,
+			tokens_sold: T::TokenBalance,
+			min_tokens_bought: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+			target_id: T::SwapId,
+		) -> dispatch::DispatchResult
+		{
+			let buyer = ensure_signed(origin)?;
+			Self::do_tokens_to_swap_input(buyer, swap_id, tokens_sold, min_tokens_bought, deadline, recipient, target_id)
+		}
 
-	// pub fn get_currency_to_token_output_price(swap: &Swap<T::AccountId, T::TokenId>, tokens_bought: T::TokenBalance)
-	// 	-> T::TokenBalance
-	// {
+		/// Converts tokens to the tokens of the swap at `target_id`.
+		///
+		/// Same as `tokens_to_tokens_output` but the bought tokens are selected
+		/// by their swap instead of their token id.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::swap_exact_out_along_path(2))]
+		pub fn tokens_to_swap_output(origin,
+			swap_id: T::SwapId,
+			tokens_bought: T::TokenBalance,
+			max_tokens_sold: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+			target_id: T::SwapId,
+		) -> dispatch::DispatchResult
+		{
+			let buyer = ensure_signed(origin)?;
+			Self::do_tokens_to_swap_output(buyer, swap_id, tokens_bought, max_tokens_sold, deadline, recipient, target_id)
+		}
 
-	// }
+		/// Swaps an exact amount of `asset_in` along `path`.
+		///
+		/// Each swap in `path` trades the asset held so far for the other side
+		/// of that swap. The user specifies the minimum amount of the final
+		/// asset to be returned. Every hop is quoted and checked before any
+		/// funds move, so the route either completes entirely or fails
+		/// without moving funds or changing reserves.
+		#[weight = FunctionOf(
+			|args: (&Vec<T::SwapId>, &Asset<T::TokenId>, &T::TokenBalance, &T::TokenBalance, &T::BlockNumber, &T::AccountId)| {
+				<T as Trait>::WeightInfo::swap_exact_in_along_path(args.0.len() as u32)
+			},
+			DispatchClass::Normal,
+			true
+		)]
+		pub fn swap_exact_in_along_path(origin,
+			path: Vec<T::SwapId>,
+			asset_in: Asset<T::TokenId>,
+			amount_in: T::TokenBalance,
+			min_out: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline >= now, Error::<T>::Deadline);
 
-	// pub fn get_token_to_currency_input_price(swap: &Swap<T::AccountId, T::TokenId>, tokens_sold: T::TokenBalance)
-	// 	-> T::TokenBalance
-	// {
+			let buyer = ensure_signed(origin)?;
 
-	// }
+			ensure!(amount_in > Zero::zero(), Error::<T>::NoTokensSwapped);
+			ensure!(min_out > Zero::zero(), Error::<T>::NoTokensSwapped);
 
-	// pub fn get_token_to_currency_output_price(swap: &Swap<T::AccountId, T::TokenId>, currency_bought: BalanceOf<T>)
-	// 	-> T::TokenBalance
-	// {
+			let hops = Self::route_exact_in(&path, asset_in, amount_in)?;
+			let amount_out = hops.last().map(|hop| hop.amount_out).unwrap_or_else(Zero::zero);
+			ensure!(amount_out >= min_out, Error::<T>::NotEnoughTokens);
 
-	// }
+			Self::execute_route(buyer, hops, recipient)
+		}
 
-	fn get_output_price(
-		output_amount: T::TokenBalance,
-		input_reserve: T::TokenBalance,
-		output_reserve: T::TokenBalance,
-	) -> T::TokenBalance
-	{
-		let numerator = input_reserve * output_amount * 1000.into();
-		let denominator = (output_reserve - output_amount) * 997.into();
-		numerator / denominator + 1.into()
-	}
+		/// Swaps `asset_in` along `path` for an exact amount of the final asset.
+		///
+		/// The user specifies the maximum amount of `asset_in` to be sold.
+		#[weight = FunctionOf(
+			|args: (&Vec<T::SwapId>, &Asset<T::TokenId>, &T::TokenBalance, &T::TokenBalance, &T::BlockNumber, &T::AccountId)| {
+				<T as Trait>::WeightInfo::swap_exact_out_along_path(args.0.len() as u32)
+			},
+			DispatchClass::Normal,
+			true
+		)]
+		pub fn swap_exact_out_along_path(origin,
+			path: Vec<T::SwapId>,
+			asset_in: Asset<T::TokenId>,
+			amount_out: T::TokenBalance,
+			max_in: T::TokenBalance,
+			deadline: T::BlockNumber,
+			recipient: T::AccountId,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline >= now, Error::<T>::Deadline);
 
-	fn get_input_price(
-		input_amount: T::TokenBalance,
-		input_reserve: T::TokenBalance,
-		output_reserve: T::TokenBalance,
-	) -> T::TokenBalance
-	{
-		let input_amount_with_fee = input_amount * 997.into();
-		let numerator = input_amount_with_fee * output_reserve;
-		let denominator = (input_reserve * 1000.into()) + input_amount_with_fee;
-		numerator / denominator
-	}
+			let buyer = ensure_signed(origin)?;
 
-	fn convert(balance_of: BalanceOf<T>) -> T::TokenBalance {
-		let m = balance_of.saturated_into::<u64>();
-		m.saturated_into()
-	}
+			ensure!(amount_out > Zero::zero(), Error::<T>::NoTokensSwapped);
+			ensure!(max_in > Zero::zero(), Error::<T>::NoTokensSwapped);
 
-	fn unconvert(token_balance: T::TokenBalance) -> BalanceOf<T> {
-		let m = token_balance.saturated_into::<u64>();
-		m.saturated_into()
-	}
+			let hops = Self::route_exact_out(&path, asset_in, amount_out)?;
+			let amount_in = hops.first().map(|hop| hop.amount_in).unwrap_or_else(Zero::zero);
+			ensure!(max_in >= amount_in, Error::<T>::TooExpensiveTokens);
 
-	fn get_token_reserve(swap: &Swap<T::AccountId, T::TokenId>) -> T::TokenBalance {
-		fungible::Module::<T>::balance_of((swap.token_id.clone(), &swap.account))
-	}
+			Self::execute_route(buyer, hops, recipient)
+		}
 
-	fn get_swap_balance(swap: &Swap<T::AccountId, T::TokenId>) -> BalanceOf<T> {
-		T::Currency::free_balance(&swap.account)
+		/// Lends `base` and `tokens` out of a swap to the caller and calls
+		/// `FlashSwapHandler`, which must pay the swap back within the call.
+		///
+		/// The loan may be repaid in either asset as long as the invariant of
+		/// the swap's curve, less the fee on what was paid in, does not fall.
+		/// If it does, whatever the swap is short of is taken back from the
+		/// caller and the call fails.
+		///
+		/// The weight of the call includes the weight the `FlashSwapHandler`
+		/// declares for the loan.
+		#[weight = FunctionOf(
+			|args: (&T::SwapId, &T::TokenBalance, &T::TokenBalance, &Vec<u8>)| {
+				<T as Trait>::WeightInfo::flash_swap()
+					.saturating_add(T::FlashSwapHandler::weight(*args.1, *args.2, args.3))
+			},
+			DispatchClass::Normal,
+			true
+		)]
+		pub fn flash_swap(origin,
+			swap_id: T::SwapId,
+			base: T::TokenBalance,
+			tokens: T::TokenBalance,
+			data: Vec<u8>,
+		) -> dispatch::DispatchResult
+		{
+			let borrower = ensure_signed(origin)?;
+
+			ensure!(!base.is_zero() || !tokens.is_zero(), Error::<T>::ZeroAmount);
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_trading_allowed(swap_id)?;
+			let base_before = Self::get_base_reserve(&swap);
+			let tokens_before = Self::get_token_reserve(&swap);
+			ensure!(base < base_before, Error::<T>::InsufficientReserve);
+			ensure!(tokens < tokens_before, Error::<T>::InsufficientReserve);
+			let held_before = (
+				Self::asset_balance(swap.base, &swap.account)?,
+				Self::asset_balance(Asset::Token(swap.token_id), &swap.account)?,
+			);
+
+			Self::update_price_oracle(swap_id)?;
+			Self::update_reference_price(swap_id, &swap)?;
+
+			// Nothing is rolled back when the call fails, so the swap is locked
+			// against everything else while the loan is out and put back by
+			// hand if it is not repaid.
+			<LockedSwaps<T>>::insert(swap_id, true);
+			let repaid = Self::lend_flash_swap(&swap, &borrower, base, tokens)
+				.and_then(|_| T::FlashSwapHandler::on_flash_swap(swap_id, &borrower, &swap.account, base, tokens, &data))
+				.and_then(|_| Self::flash_swap_repayment(swap_id, base_before, tokens_before, base, tokens));
+			<LockedSwaps<T>>::remove(swap_id);
+			let (base_in, tokens_in) = match repaid {
+				Ok(repaid) => repaid,
+				Err(e) => {
+					Self::unwind_flash_swap(swap_id, &swap, &borrower, held_before);
+					return Err(e);
+				}
+			};
+
+			Self::accrue_protocol_fee(swap_id, &swap, swap.base, base_in);
+			Self::accrue_protocol_fee(swap_id, &swap, Asset::Token(swap.token_id), tokens_in);
+			Self::sync_reserves(swap_id)?;
+
+			let event = if swap.base == Asset::Currency {
+				RawEvent::FlashSwap(
+					swap_id,
+					borrower,
+					Self::currency_amount(base),
+					tokens,
+					Self::currency_amount(base_in),
+					tokens_in,
+				)
+			} else {
+				RawEvent::PairFlashSwap(swap_id, borrower, base, tokens, base_in, tokens_in)
+			};
+			Self::deposit_event(event);
+
+			Ok(())
+		}
+
+		/// Switches a swap without liquidity to concentrated liquidity with
+		/// positions over multiples of `tick_spacing`, starting at
+		/// `sqrt_price`.
+		///
+		/// `sqrt_price` is the square root of the price in tokens per base
+		/// asset as a Q64.96 fixed point number. A concentrated liquidity swap
+		/// cannot be switched back.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_swap_curve())]
+		pub fn set_swap_concentrated(origin,
+			swap_id: T::SwapId,
+			tick_spacing: u32,
+			sqrt_price: U256,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
+
+			ensure!(
+				tick_spacing > 0 && tick_spacing <= concentrated::MAX_TICK_SPACING,
+				Error::<T>::InvalidTickSpacing
+			);
+			let tick = concentrated::tick_at_sqrt_price(sqrt_price).ok_or(Error::<T>::InvalidPrice)?;
+
+			Self::do_set_curve(swap_id, Curve::Concentrated { tick_spacing })?;
+			<ConcentratedPools<T>>::insert(swap_id, ConcentratedPool { sqrt_price, tick, ..Default::default() });
+
+			Ok(())
+		}
+
+		/// Provides `liquidity` to a concentrated liquidity swap while its
+		/// price is between `tick_lower` and `tick_upper`.
+		///
+		/// The base asset and tokens needed are rounded up and must not exceed
+		/// `max_base` and `max_tokens`. The liquidity is held as a new position
+		/// owned by the caller.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::mint_position())]
+		pub fn mint_position(origin,
+			swap_id: T::SwapId,
+			tick_lower: i32,
+			tick_upper: i32,
+			liquidity: u128,
+			max_base: T::TokenBalance,
+			max_tokens: T::TokenBalance,
+			deadline: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline > now, Error::<T>::Deadline);
+
+			let who = ensure_signed(origin)?;
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let tick_spacing = Self::tick_spacing(&swap).ok_or(Error::<T>::NotConcentratedSwap)?;
+			Self::ensure_adding_liquidity_allowed(swap_id)?;
+			let mut pool = Self::concentrated_pool(swap_id).ok_or(Error::<T>::NotConcentratedSwap)?;
+			Self::ensure_valid_range(tick_lower, tick_upper, tick_spacing)?;
+			ensure!(liquidity > 0, Error::<T>::RequestedZeroLiquidity);
+			let liquidity_delta = i128::try_from(liquidity).map_err(|_| Error::<T>::Overflow)?;
+
+			let (base_amount, token_amount) = Self::position_amounts(&pool, tick_lower, tick_upper, liquidity, true)?;
+			ensure!(base_amount <= max_base, Error::<T>::TooMuchBase);
+			ensure!(token_amount <= max_tokens, Error::<T>::TooManyTokens);
+
+			let lower = Self::updated_tick(swap_id, &pool, tick_lower, liquidity_delta, false)?;
+			let upper = Self::updated_tick(swap_id, &pool, tick_upper, liquidity_delta, true)?;
+			if tick_lower <= pool.tick && pool.tick < tick_upper {
+				pool.liquidity = pool.liquidity.checked_add(liquidity).ok_or(Error::<T>::Overflow)?;
+			}
+			let position_id = Self::position_count();
+			let next_id = position_id.checked_add(&One::one()).ok_or(Error::<T>::Overflow)?;
+
+			Self::update_price_oracle(swap_id)?;
+			if !base_amount.is_zero() {
+				Self::transfer_asset(swap.base, &who, &swap.account, base_amount, ExistenceRequirement::KeepAlive)?;
+			}
+			if !token_amount.is_zero() {
+				fungible::Module::<T>::do_transfer(swap.token_id, who.clone(), swap.account.clone(), token_amount)?;
+			}
+
+			Self::update_reserves(swap_id, base_amount, token_amount, Zero::zero(), Zero::zero())?;
+			Self::write_tick(swap_id, tick_lower, lower, tick_spacing);
+			Self::write_tick(swap_id, tick_upper, upper, tick_spacing);
+			let (fee_growth_inside_base, fee_growth_inside_tokens) =
+				Self::fee_growth_inside(swap_id, &pool, tick_lower, tick_upper);
+			<ConcentratedPools<T>>::insert(swap_id, pool);
+			<Positions<T>>::insert(position_id, Position {
+				owner: who.clone(),
+				swap_id,
+				tick_lower,
+				tick_upper,
+				liquidity,
+				fee_growth_inside_base,
+				fee_growth_inside_tokens,
+				base_owed: Zero::zero(),
+				tokens_owed: Zero::zero(),
+			});
+			<PositionCount<T>>::put(next_id);
+
+			Self::deposit_event(RawEvent::PositionMinted(
+				swap_id,
+				position_id,
+				who,
+				tick_lower,
+				tick_upper,
+				liquidity,
+				base_amount,
+				token_amount,
+			));
+
+			Ok(())
+		}
+
+		/// Withdraws `liquidity` from a position along with the fees it has
+		/// earned. The position is removed once all of its liquidity is gone.
+		///
+		/// The base asset and tokens returned, before fees, are rounded down
+		/// and must be at least `min_base` and `min_tokens`.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::burn_position())]
+		pub fn burn_position(origin,
+			position_id: T::PositionId,
+			liquidity: u128,
+			min_base: T::TokenBalance,
+			min_tokens: T::TokenBalance,
+			deadline: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(deadline > now, Error::<T>::Deadline);
+
+			let who = ensure_signed(origin)?;
+
+			let mut position = Self::positions(position_id).ok_or(Error::<T>::NoPositionExists)?;
+			ensure!(position.owner == who, Error::<T>::NotPositionOwner);
+			ensure!(liquidity > 0, Error::<T>::BurnZeroShares);
+			ensure!(liquidity <= position.liquidity, Error::<T>::InsufficientLiquidity);
+			let liquidity_delta = i128::try_from(liquidity).map_err(|_| Error::<T>::Overflow)?;
+
+			let swap_id = position.swap_id;
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let tick_spacing = Self::tick_spacing(&swap).ok_or(Error::<T>::NotConcentratedSwap)?;
+			let mut pool = Self::concentrated_pool(swap_id).ok_or(Error::<T>::NotConcentratedSwap)?;
+			let (tick_lower, tick_upper) = (position.tick_lower, position.tick_upper);
+
+			let (base_amount, token_amount) = Self::position_amounts(&pool, tick_lower, tick_upper, liquidity, false)?;
+			ensure!(base_amount >= min_base, Error::<T>::NotEnoughCurrency);
+			ensure!(token_amount >= min_tokens, Error::<T>::NotEnoughTokens);
+
+			Self::settle_position_fees(&mut position, Self::fee_growth_inside(swap_id, &pool, tick_lower, tick_upper))?;
+			let (base_fees, token_fees) = (position.base_owed, position.tokens_owed);
+			let base_total = base_amount.checked_add(&base_fees).ok_or(Error::<T>::Overflow)?;
+			let token_total = token_amount.checked_add(&token_fees).ok_or(Error::<T>::Overflow)?;
+
+			let lower = Self::updated_tick(swap_id, &pool, tick_lower, -liquidity_delta, false)?;
+			let upper = Self::updated_tick(swap_id, &pool, tick_upper, -liquidity_delta, true)?;
+			if tick_lower <= pool.tick && pool.tick < tick_upper {
+				pool.liquidity = pool.liquidity.checked_sub(liquidity).ok_or(Error::<T>::Overflow)?;
+			}
+
+			Self::update_price_oracle(swap_id)?;
+			if !base_total.is_zero() {
+				Self::transfer_asset(swap.base, &swap.account, &who, base_total, ExistenceRequirement::AllowDeath)?;
+			}
+			if !token_total.is_zero() {
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), who.clone(), token_total)?;
+			}
+
+			Self::update_reserves(swap_id, Zero::zero(), Zero::zero(), base_total, token_total)?;
+			Self::write_tick(swap_id, tick_lower, lower, tick_spacing);
+			Self::write_tick(swap_id, tick_upper, upper, tick_spacing);
+			<ConcentratedPools<T>>::insert(swap_id, pool);
+			position.liquidity -= liquidity;
+			position.base_owed = Zero::zero();
+			position.tokens_owed = Zero::zero();
+			if position.liquidity == 0 {
+				<Positions<T>>::remove(position_id);
+			} else {
+				<Positions<T>>::insert(position_id, position);
+			}
+
+			Self::deposit_event(RawEvent::PositionBurned(swap_id, position_id, who.clone(), liquidity, base_amount, token_amount));
+			if !base_fees.is_zero() || !token_fees.is_zero() {
+				Self::deposit_event(RawEvent::PositionFeesCollected(swap_id, position_id, who, base_fees, token_fees));
+			}
+
+			Ok(())
+		}
+
+		/// Pays out the fees a position has earned without touching its
+		/// liquidity.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::collect_position_fees())]
+		pub fn collect_position_fees(origin,
+			position_id: T::PositionId,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			let mut position = Self::positions(position_id).ok_or(Error::<T>::NoPositionExists)?;
+			ensure!(position.owner == who, Error::<T>::NotPositionOwner);
+
+			let swap_id = position.swap_id;
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let pool = Self::concentrated_pool(swap_id).ok_or(Error::<T>::NotConcentratedSwap)?;
+			let fee_growth_inside = Self::fee_growth_inside(swap_id, &pool, position.tick_lower, position.tick_upper);
+			Self::settle_position_fees(&mut position, fee_growth_inside)?;
+			let (base_fees, token_fees) = (position.base_owed, position.tokens_owed);
+			ensure!(!base_fees.is_zero() || !token_fees.is_zero(), Error::<T>::NoFeesOwed);
+
+			Self::update_price_oracle(swap_id)?;
+			if !base_fees.is_zero() {
+				Self::transfer_asset(swap.base, &swap.account, &who, base_fees, ExistenceRequirement::AllowDeath)?;
+			}
+			if !token_fees.is_zero() {
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), who.clone(), token_fees)?;
+			}
+			Self::update_reserves(swap_id, Zero::zero(), Zero::zero(), base_fees, token_fees)?;
+
+			position.base_owed = Zero::zero();
+			position.tokens_owed = Zero::zero();
+			<Positions<T>>::insert(position_id, position);
+
+			Self::deposit_event(RawEvent::PositionFeesCollected(swap_id, position_id, who, base_fees, token_fees));
+
+			Ok(())
+		}
+
+		/// Sets the reserves of a swap to what its account holds, less
+		/// unclaimed protocol fees, counting anything sent to it directly.
+		///
+		/// Anyone may trigger the sync. Concentrated liquidity swaps cannot be
+		/// synced, as their reserves are what their positions are owed.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::sync())]
+		pub fn sync(origin,
+			swap_id: T::SwapId,
+		) -> dispatch::DispatchResult
+		{
+			ensure_signed(origin)?;
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_unlocked(swap_id)?;
+			Self::update_price_oracle(swap_id)?;
+			Self::sync_reserves(swap_id)
+		}
+
+		/// Sends whatever a swap account holds beyond its reserves and
+		/// unclaimed protocol fees to `recipient`.
+		///
+		/// Anyone may trigger the skim. Concentrated liquidity swaps cannot be
+		/// skimmed, as their reserves are what their positions are owed.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::skim())]
+		pub fn skim(origin,
+			swap_id: T::SwapId,
+			recipient: T::AccountId,
+		) -> dispatch::DispatchResult
+		{
+			ensure_signed(origin)?;
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_unlocked(swap_id)?;
+			let (base_held, tokens_held) = Self::held_balances(&swap)?;
+			let base = base_held.saturating_sub(swap.base_reserve);
+			let tokens = tokens_held.saturating_sub(swap.token_reserve);
+
+			if !base.is_zero() {
+				Self::transfer_asset(swap.base, &swap.account, &recipient, base, ExistenceRequirement::AllowDeath)?;
+			}
+			if !tokens.is_zero() {
+				fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), recipient.clone(), tokens)?;
+			}
+
+			Self::deposit_event(RawEvent::Skimmed(swap_id, recipient, base, tokens));
+
+			Ok(())
+		}
+
+		/// Starts rewarding the stakers of a swap's liquidity shares with
+		/// `reward_per_block` of `reward_token` in every block after `start`
+		/// up to and including `end`.
+		///
+		/// The rewards are paid from the farm account, which anyone can fund
+		/// with `fund_farm`.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::create_farm())]
+		pub fn create_farm(origin,
+			swap_id: T::SwapId,
+			reward_token: T::TokenId,
+			reward_per_block: T::TokenBalance,
+			start: T::BlockNumber,
+			end: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			ensure_root(origin)?;
+
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			ensure!(!Farms::<T>::contains_key(swap_id), Error::<T>::FarmAlreadyExists);
+			ensure!(
+				fungible::Module::<T>::exists(reward_token) && reward_token != swap.swap_token,
+				Error::<T>::InvalidRewardToken
+			);
+			ensure!(!reward_per_block.is_zero(), Error::<T>::ZeroAmount);
+			let now = system::Module::<T>::block_number();
+			ensure!(start < end && end > now, Error::<T>::InvalidFarmSchedule);
+
+			<Farms<T>>::insert(swap_id, Farm {
+				reward_token,
+				reward_per_block,
+				start,
+				end,
+				total_staked: Zero::zero(),
+				reward_per_share: U256::zero(),
+				reward_updated: start.max(now),
+			});
+
+			Self::deposit_event(RawEvent::FarmCreated(swap_id, reward_token, reward_per_block, start, end));
+
+			Ok(())
+		}
+
+		/// Sends `amount` of the reward token of a farm to its account.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::fund_farm())]
+		pub fn fund_farm(origin,
+			swap_id: T::SwapId,
+			amount: T::TokenBalance,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+			let farm = Self::farms(swap_id).ok_or(Error::<T>::NoFarmExists)?;
+
+			fungible::Module::<T>::do_transfer(farm.reward_token, who.clone(), Self::farm_account(swap_id), amount)?;
+
+			Self::deposit_event(RawEvent::FarmFunded(swap_id, who, amount));
+
+			Ok(())
+		}
+
+		/// Stakes `amount` of a swap's liquidity shares in its farm.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::stake())]
+		pub fn stake(origin,
+			swap_id: T::SwapId,
+			amount: T::TokenBalance,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let mut farm = Self::farms(swap_id).ok_or(Error::<T>::NoFarmExists)?;
+			Self::update_farm(&mut farm)?;
+			let mut stake = Self::stakes((swap_id, who.clone())).unwrap_or_default();
+			Self::settle_stake(&farm, &mut stake)?;
+			stake.amount = stake.amount.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+			farm.total_staked = farm.total_staked.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+
+			fungible::Module::<T>::do_transfer(swap.swap_token, who.clone(), Self::farm_account(swap_id), amount)?;
+
+			<Farms<T>>::insert(swap_id, farm);
+			<Stakes<T>>::insert((swap_id, who.clone()), stake);
+
+			Self::deposit_event(RawEvent::Staked(swap_id, who, amount));
+
+			Ok(())
+		}
+
+		/// Withdraws `amount` of staked liquidity shares from a farm. Rewards
+		/// earned so far stay owed until harvested.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::unstake())]
+		pub fn unstake(origin,
+			swap_id: T::SwapId,
+			amount: T::TokenBalance,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let mut farm = Self::farms(swap_id).ok_or(Error::<T>::NoFarmExists)?;
+			let mut stake = Self::stakes((swap_id, who.clone())).ok_or(Error::<T>::NotEnoughStaked)?;
+			ensure!(amount <= stake.amount, Error::<T>::NotEnoughStaked);
+			Self::update_farm(&mut farm)?;
+			Self::settle_stake(&farm, &mut stake)?;
+			stake.amount -= amount;
+			farm.total_staked = farm.total_staked.saturating_sub(amount);
+
+			fungible::Module::<T>::do_transfer(swap.swap_token, Self::farm_account(swap_id), who.clone(), amount)?;
+
+			<Farms<T>>::insert(swap_id, farm);
+			Self::write_stake(swap_id, &who, stake);
+
+			Self::deposit_event(RawEvent::Unstaked(swap_id, who, amount));
+
+			Ok(())
+		}
+
+		/// Pays out the rewards a stake has earned, as far as the farm account
+		/// can cover them. The rest stays owed.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::harvest())]
+		pub fn harvest(origin,
+			swap_id: T::SwapId,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			let mut farm = Self::farms(swap_id).ok_or(Error::<T>::NoFarmExists)?;
+			let mut stake = Self::stakes((swap_id, who.clone())).ok_or(Error::<T>::NoRewardsOwed)?;
+			Self::update_farm(&mut farm)?;
+			Self::settle_stake(&farm, &mut stake)?;
+			ensure!(!stake.rewards_owed.is_zero(), Error::<T>::NoRewardsOwed);
+
+			let farm_account = Self::farm_account(swap_id);
+			let available = fungible::Module::<T>::balance_of((farm.reward_token, &farm_account));
+			let rewards = stake.rewards_owed.min(available);
+			ensure!(!rewards.is_zero(), Error::<T>::FarmUnderfunded);
+
+			fungible::Module::<T>::do_transfer(farm.reward_token, farm_account, who.clone(), rewards)?;
+
+			stake.rewards_owed -= rewards;
+			<Farms<T>>::insert(swap_id, farm);
+			Self::write_stake(swap_id, &who, stake);
+
+			Self::deposit_event(RawEvent::RewardsHarvested(swap_id, who, rewards));
+
+			Ok(())
+		}
+
+		/// Escrows `amount` of the `side` of a swap to be sold once the swap
+		/// pays at least `limit_price` of the other side for it, scaled by
+		/// `PRICE_PRECISION`, up to and including block `expiry`.
+		///
+		/// Orders are not filled by the pallet itself. Any keeper can fill one
+		/// with `execute_order` and earns `OrderTip` of it for doing so.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::place_limit_order())]
+		pub fn place_limit_order(origin,
+			swap_id: T::SwapId,
+			side: Side,
+			amount: T::TokenBalance,
+			limit_price: u128,
+			expiry: T::BlockNumber,
+		) -> dispatch::DispatchResult
+		{
+			let now = system::Module::<T>::block_number();
+			ensure!(expiry >= now, Error::<T>::Deadline);
+
+			let owner = ensure_signed(origin)?;
+
+			ensure!(!amount.is_zero(), Error::<T>::ZeroAmount);
+			ensure!(limit_price > 0, Error::<T>::InvalidLimitPrice);
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_trading_allowed(swap_id)?;
+			let order_id = Self::order_count();
+			let next_id = order_id.checked_add(&One::one()).ok_or(Error::<T>::Overflow)?;
+
+			let asset = Self::side_asset(&swap, side);
+			Self::transfer_asset(asset, &owner, &Self::order_account(order_id), amount, ExistenceRequirement::KeepAlive)?;
+
+			<Orders<T>>::insert(order_id, LimitOrder {
+				owner: owner.clone(),
+				swap_id,
+				side,
+				amount,
+				limit_price,
+				expiry,
+			});
+			<OrderCount<T>>::put(next_id);
+
+			Self::deposit_event(RawEvent::OrderPlaced(order_id, swap_id, owner, side, amount, limit_price, expiry));
+
+			Ok(())
+		}
+
+		/// Fills a limit order against its swap, paying the caller `OrderTip`
+		/// of the order.
+		///
+		/// The owner must receive at least the limit price for the whole
+		/// order, tip included.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::execute_order())]
+		pub fn execute_order(origin,
+			order_id: T::OrderId,
+		) -> dispatch::DispatchResult
+		{
+			let keeper = ensure_signed(origin)?;
+
+			let order = Self::orders(order_id).ok_or(Error::<T>::NoOrderExists)?;
+			let now = system::Module::<T>::block_number();
+			ensure!(now <= order.expiry, Error::<T>::OrderExpired);
+
+			let swap_id = order.swap_id;
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+			Self::ensure_trading_allowed(swap_id)?;
+			let asset_in = Self::side_asset(&swap, order.side);
+			let tip = T::OrderTip::get() * order.amount;
+			let sold = order.amount - tip;
+			let (asset_out, input_reserve, output_reserve) = Self::hop_side(&swap, asset_in)?;
+			let bought = Self::swap_input_price(swap_id, &swap, asset_in, sold, input_reserve, output_reserve)?;
+
+			let limit = Self::to_u256(order.amount)?
+				.checked_mul(U256::from(order.limit_price))
+				.ok_or(Error::<T>::Overflow)?
+				/ U256::from(PRICE_PRECISION);
+			ensure!(!bought.is_zero() && Self::to_u256(bought)? >= limit, Error::<T>::LimitPriceNotReached);
+			Self::ensure_within_circuit_breaker(swap_id, &swap, asset_in, sold, bought, true)?;
+			let escrow = Self::order_account(order_id);
+			Self::ensure_can_pay_out(asset_out, &swap.account, &order.owner, bought)?;
+			if !tip.is_zero() {
+				Self::ensure_can_pay_out(asset_in, &escrow, &keeper, tip)?;
+			}
+
+			// The escrow is emptied in one transfer, as a currency balance left
+			// below the existential deposit would be lost. The swap passes the
+			// tip on, which stays out of its reserves.
+			Self::update_price_oracle(swap_id)?;
+			Self::transfer_asset(asset_in, &escrow, &swap.account, order.amount, ExistenceRequirement::AllowDeath)?;
+			Self::apply_trade(swap_id, &swap, asset_in, sold, bought, true)?;
+			Self::transfer_asset(asset_out, &swap.account, &order.owner, bought, ExistenceRequirement::AllowDeath)?;
+			Self::record_trade(swap_id, &swap, &order.owner, &order.owner, asset_in, sold, bought);
+			if !tip.is_zero() {
+				Self::transfer_asset(asset_in, &swap.account, &keeper, tip, ExistenceRequirement::AllowDeath)?;
+			}
+
+			<Orders<T>>::remove(order_id);
+
+			Self::deposit_event(RawEvent::OrderExecuted(order_id, keeper, sold, bought, tip));
+
+			Ok(())
+		}
+
+		/// Cancels a limit order and returns its funds to the owner.
+		///
+		/// Only the owner can cancel an open order, while anyone can clear one
+		/// that has expired.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::cancel_order())]
+		pub fn cancel_order(origin,
+			order_id: T::OrderId,
+		) -> dispatch::DispatchResult
+		{
+			let who = ensure_signed(origin)?;
+
+			let order = Self::orders(order_id).ok_or(Error::<T>::NoOrderExists)?;
+			let now = system::Module::<T>::block_number();
+			ensure!(who == order.owner || now > order.expiry, Error::<T>::NotOrderOwner);
+
+			let swap = Self::swaps(order.swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let asset = Self::side_asset(&swap, order.side);
+			Self::transfer_asset(asset, &Self::order_account(order_id), &order.owner, order.amount, ExistenceRequirement::AllowDeath)?;
+
+			<Orders<T>>::remove(order_id);
+
+			Self::deposit_event(RawEvent::OrderCancelled(order_id));
+
+			Ok(())
+		}
+
+		/// Pauses or resumes trading through every swap.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_paused())]
+		pub fn set_trading_paused(origin,
+			paused: bool,
+		) -> dispatch::DispatchResult
+		{
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			TradingPaused::put(paused);
+
+			Self::deposit_event(RawEvent::TradingPauseChanged(paused));
+
+			Ok(())
+		}
+
+		/// Pauses or resumes adding liquidity to every swap.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_paused())]
+		pub fn set_liquidity_paused(origin,
+			paused: bool,
+		) -> dispatch::DispatchResult
+		{
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			LiquidityPaused::put(paused);
+
+			Self::deposit_event(RawEvent::LiquidityPauseChanged(paused));
+
+			Ok(())
+		}
+
+		/// Pauses or resumes trading through and adding liquidity to a single
+		/// swap. Liquidity can always be removed.
+		///
+		/// Resuming a swap starts a new circuit breaker window.
+		#[weight = SimpleDispatchInfo::FixedNormal(<T as Trait>::WeightInfo::set_paused())]
+		pub fn set_swap_paused(origin,
+			swap_id: T::SwapId,
+			paused: bool,
+		) -> dispatch::DispatchResult
+		{
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(<Swaps<T>>::contains_key(swap_id), Error::<T>::NoSwapExists);
+			if paused {
+				<PausedSwaps<T>>::insert(swap_id, true);
+			} else {
+				<PausedSwaps<T>>::remove(swap_id);
+				<ReferencePrices<T>>::remove(swap_id);
+			}
+
+			Self::deposit_event(RawEvent::SwapPauseChanged(swap_id, paused));
+
+			Ok(())
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Creates a swap for a pair of assets, with the smaller asset as its base.
+	fn do_create_swap(sender: T::AccountId, asset_a: Asset<T::TokenId>, asset_b: Asset<T::TokenId>)
+		-> dispatch::DispatchResult
+	{
+		let pair = Self::sort_pair(asset_a, asset_b);
+		let (base, token_id) = match pair {
+			(base, Asset::Token(token_id)) if base != pair.1 => (base, token_id),
+			_ => return Err(Error::<T>::IdenticalAssets.into()),
+		};
+		ensure!(!Pairs::<T>::contains_key(pair), Error::<T>::SwapAlreadyExists);
+
+		let swap_id = Self::swap_count();
+		let next_id = swap_id.checked_add(&One::one())
+			.ok_or("Overflow")?;
+
+		let swap_token_id = fungible::Module::<T>::create_token(sender, Zero::zero());
+
+		let account: T::AccountId = MODULE_ID.into_sub_account(swap_token_id);
+		let now = system::Module::<T>::block_number();
+
+		let new_swap = Swap {
+			base,
+			token_id: token_id,
+			swap_token: swap_token_id,
+			account: account.clone(),
+			fee: None,
+			base_reserve: Zero::zero(),
+			token_reserve: Zero::zero(),
+			protocol_base: Zero::zero(),
+			protocol_tokens: Zero::zero(),
+			token_price_cumulative: U256::zero(),
+			base_price_cumulative: U256::zero(),
+			price_updated: now,
+			curve: Curve::ConstantProduct,
+		};
+
+		<Pairs<T>>::insert(pair, swap_id);
+		<Swaps<T>>::insert(swap_id, new_swap);
+		<PriceObservations<T>>::insert(swap_id, vec![PriceObservation {
+			block: now,
+			token_price_cumulative: U256::zero(),
+			base_price_cumulative: U256::zero(),
+		}]);
+		<SwapCount<T>>::put(next_id);
+
+		Self::deposit_event(RawEvent::SwapCreated(swap_id, account));
+
+		Ok(())
+	}
+
+	/// Creates a swap at genesis with reserves of `amount_a` of `asset_a` and
+	/// `amount_b` of `asset_b`, all of whose shares belong to `owner`.
+	#[cfg(feature = "std")]
+	fn create_genesis_pool(
+		owner: T::AccountId,
+		asset_a: Asset<T::TokenId>,
+		amount_a: T::TokenBalance,
+		asset_b: Asset<T::TokenId>,
+		amount_b: T::TokenBalance,
+	) -> dispatch::DispatchResult
+	{
+		ensure!(!amount_a.is_zero() && !amount_b.is_zero(), Error::<T>::ZeroAmount);
+		ensure!(
+			amount_a.min(amount_b) >= T::MinimumDeposit::get()
+				&& amount_a.min(amount_b) > T::MinimumLiquidity::get(),
+			Error::<T>::DepositTooSmall
+		);
+		for asset in &[asset_a, asset_b] {
+			if let Asset::Token(id) = asset {
+				ensure!(*id < fungible::Module::<T>::token_count(), "Genesis pool token does not exist");
+			}
+		}
+
+		let swap_id = Self::swap_count();
+		Self::do_create_swap(owner.clone(), asset_a, asset_b)?;
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let (base_amount, token_amount) = if asset_a == swap.base {
+			(amount_a, amount_b)
+		} else {
+			(amount_b, amount_a)
+		};
+
+		match swap.base {
+			Asset::Currency => {
+				let _ = T::Currency::deposit_creating(&swap.account, Self::unconvert(base_amount)?);
+			},
+			Asset::Token(id) => fungible::Module::<T>::mint(id, swap.account.clone(), base_amount)?,
+		}
+		fungible::Module::<T>::mint(swap.token_id, swap.account.clone(), token_amount)?;
+		Self::update_reserves(swap_id, base_amount, token_amount, Zero::zero(), Zero::zero())?;
+		// Matches the shares minted by the first `add_liquidity`.
+		Self::lock_minimum_liquidity(&swap)?;
+		fungible::Module::<T>::mint(swap.swap_token, owner.clone(), base_amount - T::MinimumLiquidity::get())?;
+
+		Self::deposit_liquidity_added(swap_id, &swap, owner, base_amount, token_amount);
+
+		Ok(())
+	}
+
+	/// The account holding the liquidity shares locked by first deposits.
+	pub fn locked_liquidity_account() -> T::AccountId {
+		LOCKED_LIQUIDITY_ID.into_account()
+	}
+
+	/// The account holding the staked shares and rewards of the farm of a
+	/// swap.
+	pub fn farm_account(swap_id: T::SwapId) -> T::AccountId {
+		FARM_ID.into_sub_account(swap_id)
+	}
+
+	/// The account holding the funds of an open limit order.
+	pub fn order_account(order_id: T::OrderId) -> T::AccountId {
+		ORDER_ID.into_sub_account(order_id)
+	}
+
+	/// The rewards `who` could harvest from the farm of a swap, were the farm
+	/// funded well enough.
+	pub fn pending_rewards(swap_id: T::SwapId, who: &T::AccountId) -> Result<T::TokenBalance, DispatchError> {
+		let mut farm = Self::farms(swap_id).ok_or(Error::<T>::NoFarmExists)?;
+		let mut stake = Self::stakes((swap_id, who.clone())).unwrap_or_default();
+		Self::update_farm(&mut farm)?;
+		Self::settle_stake(&farm, &mut stake)?;
+		Ok(stake.rewards_owed)
+	}
+
+	/// Mints `MinimumLiquidity` shares of a swap to the locked account.
+	fn lock_minimum_liquidity(swap: &SwapOf<T>) -> dispatch::DispatchResult {
+		let minimum_liquidity = T::MinimumLiquidity::get();
+		if minimum_liquidity.is_zero() {
+			return Ok(());
+		}
+		fungible::Module::<T>::mint(swap.swap_token, Self::locked_liquidity_account(), minimum_liquidity)
+	}
+
+	/// Switches the curve of a swap, which must not have liquidity yet.
+	fn do_set_curve(swap_id: T::SwapId, curve: Curve<T::BlockNumber>) -> dispatch::DispatchResult {
+		let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		// Positions hold no shares, so a concentrated swap is never switched.
+		ensure!(Self::tick_spacing(&swap).is_none(), Error::<T>::ConcentratedSwap);
+		// Switching the curve of a live pool would move its price at once.
+		ensure!(
+			fungible::Module::<T>::total_supply(swap.swap_token).is_zero(),
+			Error::<T>::SwapHasLiquidity
+		);
+
+		swap.curve = curve;
+		<Swaps<T>>::insert(swap_id, swap);
+
+		Self::deposit_event(RawEvent::SwapCurveChanged(swap_id, curve));
+
+		Ok(())
+	}
+
+	/// Orders a pair of assets with the base asset first.
+	fn sort_pair(asset_a: Asset<T::TokenId>, asset_b: Asset<T::TokenId>) -> (Asset<T::TokenId>, Asset<T::TokenId>) {
+		if asset_a <= asset_b { (asset_a, asset_b) } else { (asset_b, asset_a) }
+	}
+
+	/// Quotes the tokens bought when selling exactly `currency_sold` to a swap.
+	pub fn get_currency_to_token_input_price(swap_id: T::SwapId, currency_sold: BalanceOf<T>)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		Self::currency_to_token_input_price(swap_id, &swap, currency_sold)
+	}
+
+	/// Quotes the currency sold when buying exactly `tokens_bought` from a swap.
+	pub fn get_currency_to_token_output_price(swap_id: T::SwapId, tokens_bought: T::TokenBalance)
+		-> Result<BalanceOf<T>, DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		Self::currency_to_token_output_price(swap_id, &swap, tokens_bought)
+	}
+
+	/// Quotes the currency bought when selling exactly `tokens_sold` to a swap.
+	pub fn get_token_to_currency_input_price(swap_id: T::SwapId, tokens_sold: T::TokenBalance)
+		-> Result<BalanceOf<T>, DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		Self::token_to_currency_input_price(swap_id, &swap, tokens_sold)
+	}
+
+	/// Quotes the tokens sold when buying exactly `currency_bought` from a swap.
+	pub fn get_token_to_currency_output_price(swap_id: T::SwapId, currency_bought: BalanceOf<T>)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		Self::token_to_currency_output_price(swap_id, &swap, currency_bought)
+	}
+
+	/// Quotes the final output of selling exactly `amount_in` of `asset_in`
+	/// along `path`.
+	pub fn quote_exact_in(path: Vec<T::SwapId>, asset_in: Asset<T::TokenId>, amount_in: T::TokenBalance)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		let hops = Self::route_exact_in(&path, asset_in, amount_in)?;
+		Ok(hops.last().map(|hop| hop.amount_out).unwrap_or_else(Zero::zero))
+	}
+
+	/// Quotes the amount of `asset_in` sold when buying exactly `amount_out` of
+	/// the final asset along `path`.
+	pub fn quote_exact_out(path: Vec<T::SwapId>, asset_in: Asset<T::TokenId>, amount_out: T::TokenBalance)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		let hops = Self::route_exact_out(&path, asset_in, amount_out)?;
+		Ok(hops.first().map(|hop| hop.amount_in).unwrap_or_else(Zero::zero))
+	}
+
+	/// The base asset and token reserves of a swap.
+	pub fn get_reserves(swap_id: T::SwapId) -> Option<(T::TokenBalance, T::TokenBalance)> {
+		Self::swaps(swap_id).map(|swap| (Self::get_base_reserve(&swap), Self::get_token_reserve(&swap)))
+	}
+
+	/// The base asset and tokens set aside for the protocol in a swap and not
+	/// yet claimed.
+	pub fn unclaimed_protocol_fees(swap_id: T::SwapId) -> Option<(T::TokenBalance, T::TokenBalance)> {
+		Self::swaps(swap_id).map(|swap| (swap.protocol_base, swap.protocol_tokens))
+	}
+
+	/// A summary of a swap and its reserves.
+	pub fn pool_info(swap_id: T::SwapId) -> Option<PoolInfoOf<T>> {
+		Self::swaps(swap_id).map(|swap| PoolInfo {
+			base_reserve: Self::get_base_reserve(&swap),
+			token_reserve: Self::get_token_reserve(&swap),
+			total_shares: fungible::Module::<T>::total_supply(swap.swap_token),
+			fee: Self::swap_fee(&swap),
+			amplification: Self::amplification(&swap),
+			base_weight: match swap.curve {
+				Curve::Weighted { base_weight } => Some(base_weight),
+				_ => None,
+			},
+			concentrated: Self::concentrated_pool(swap_id),
+			base: swap.base,
+			token_id: swap.token_id,
+			swap_token: swap.swap_token,
+			account: swap.account,
+		})
+	}
+
+	/// Summaries of every swap, in order of creation.
+	pub fn pools() -> Vec<(T::SwapId, PoolInfoOf<T>)> {
+		let count = Self::swap_count();
+		let mut pools = Vec::new();
+		let mut swap_id = T::SwapId::zero();
+		while swap_id < count {
+			if let Some(info) = Self::pool_info(swap_id) {
+				pools.push((swap_id, info));
+			}
+			swap_id += One::one();
+		}
+		pools
+	}
+
+	/// The time-weighted average prices of a swap over at least the last
+	/// `window` blocks, as (base per token, tokens per base) scaled by
+	/// `PRICE_PRECISION`.
+	///
+	/// The average starts from the latest observation at or before `window`
+	/// blocks ago and ignores trades in the current block.
+	pub fn consult(swap_id: T::SwapId, window: T::BlockNumber) -> Result<(u128, u128), DispatchError> {
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let now = system::Module::<T>::block_number();
+		let target = now.saturating_sub(window);
+
+		let observations = Self::price_observations(swap_id);
+		let start = observations.iter().rev()
+			.find(|observation| observation.block <= target)
+			.ok_or(Error::<T>::InsufficientHistory)?;
+		let elapsed = now.saturating_sub(start.block);
+		ensure!(!elapsed.is_zero(), Error::<T>::InsufficientHistory);
+
+		let (token_price_cumulative, base_price_cumulative) = Self::cumulative_prices(swap_id, &swap, now)?;
+		let elapsed = U256::from(elapsed.saturated_into::<u128>());
+		let average = |current: U256, start: U256| -> Result<u128, Error<T>> {
+			let average = current.overflowing_sub(start).0 / elapsed;
+			ensure!(average <= U256::from(u128::max_value()), Error::<T>::Overflow);
+			Ok(average.low_u128())
+		};
+
+		Ok((
+			average(token_price_cumulative, start.token_price_cumulative)?,
+			average(base_price_cumulative, start.base_price_cumulative)?,
+		))
+	}
+
+	/// The swap trading `token_id` against the native currency, if any.
+	pub fn get_swap_by_token(token_id: T::TokenId) -> Option<T::SwapId> {
+		Self::get_swap_by_pair(Asset::Currency, Asset::Token(token_id))
+	}
+
+	/// The swap trading `asset_a` against `asset_b`, in either order, if any.
+	pub fn get_swap_by_pair(asset_a: Asset<T::TokenId>, asset_b: Asset<T::TokenId>) -> Option<T::SwapId> {
+		Self::pair_swap(Self::sort_pair(asset_a, asset_b))
+	}
+
+	/// The base asset and tokens that burning `shares` of a swap would return.
+	pub fn lp_share_value(swap_id: T::SwapId, shares: T::TokenBalance)
+		-> Result<(T::TokenBalance, T::TokenBalance), DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		Self::liquidity_value(&swap, shares)
+	}
+
+	fn liquidity_value(swap: &SwapOf<T>, shares: T::TokenBalance)
+		-> Result<(T::TokenBalance, T::TokenBalance), DispatchError>
+	{
+		let total_liquidity = fungible::Module::<T>::total_supply(swap.swap_token.clone());
+		ensure!(total_liquidity > Zero::zero(), Error::<T>::NoLiquidity);
+
+		let base_amount = Self::mul_div(shares, Self::get_base_reserve(swap), total_liquidity)?;
+		let token_amount = Self::mul_div(shares, Self::get_token_reserve(swap), total_liquidity)?;
+		Ok((base_amount, token_amount))
+	}
+
+	fn currency_to_token_input_price(swap_id: T::SwapId, swap: &SwapOf<T>, currency_sold: BalanceOf<T>)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(swap.base == Asset::Currency, Error::<T>::NotCurrencySwap);
+		if currency_sold.is_zero() { return Ok(Zero::zero()); }
+
+		let token_reserve = Self::get_token_reserve(swap);
+		let base_reserve = Self::get_base_reserve(swap);
+		Self::swap_input_price(swap_id, swap, Asset::Currency, Self::convert(currency_sold)?, base_reserve, token_reserve)
+	}
+
+	fn currency_to_token_output_price(swap_id: T::SwapId, swap: &SwapOf<T>, tokens_bought: T::TokenBalance)
+		-> Result<BalanceOf<T>, DispatchError>
+	{
+		ensure!(swap.base == Asset::Currency, Error::<T>::NotCurrencySwap);
+		if tokens_bought.is_zero() { return Ok(Zero::zero()); }
+
+		let token_reserve = Self::get_token_reserve(swap);
+		let base_reserve = Self::get_base_reserve(swap);
+		let currency_sold = Self::swap_output_price(swap_id, swap, Asset::Currency, tokens_bought, base_reserve, token_reserve)?;
+		Ok(Self::unconvert(currency_sold)?)
+	}
+
+	fn token_to_currency_input_price(swap_id: T::SwapId, swap: &SwapOf<T>, tokens_sold: T::TokenBalance)
+		-> Result<BalanceOf<T>, DispatchError>
+	{
+		ensure!(swap.base == Asset::Currency, Error::<T>::NotCurrencySwap);
+		if tokens_sold.is_zero() { return Ok(Zero::zero()); }
+
+		let token_reserve = Self::get_token_reserve(swap);
+		let base_reserve = Self::get_base_reserve(swap);
+		let token = Asset::Token(swap.token_id);
+		let currency_bought = Self::swap_input_price(swap_id, swap, token, tokens_sold, token_reserve, base_reserve)?;
+		Ok(Self::unconvert(currency_bought)?)
+	}
+
+	fn token_to_currency_output_price(swap_id: T::SwapId, swap: &SwapOf<T>, currency_bought: BalanceOf<T>)
+		-> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(swap.base == Asset::Currency, Error::<T>::NotCurrencySwap);
+		if currency_bought.is_zero() { return Ok(Zero::zero()); }
+
+		let token_reserve = Self::get_token_reserve(swap);
+		let base_reserve = Self::get_base_reserve(swap);
+		let token = Asset::Token(swap.token_id);
+		Self::swap_output_price(swap_id, swap, token, Self::convert(currency_bought)?, token_reserve, base_reserve)
+	}
+
+	/// Sells `tokens_sold` of the tokens in `swap_id` for its base asset and
+	/// uses it to buy the tokens in `target_id`.
+	fn do_tokens_to_swap_input(
+		buyer: T::AccountId,
+		swap_id: T::SwapId,
+		tokens_sold: T::TokenBalance,
+		min_tokens_bought: T::TokenBalance,
+		deadline: T::BlockNumber,
+		recipient: T::AccountId,
+		target_id: T::SwapId,
+	) -> dispatch::DispatchResult
+	{
+		let now = system::Module::<T>::block_number();
+		ensure!(deadline >= now, Error::<T>::Deadline);
+
+		ensure!(tokens_sold > Zero::zero(), Error::<T>::NoTokensSwapped);
+		ensure!(min_tokens_bought > Zero::zero(), Error::<T>::NoTokensSwapped);
+		ensure!(swap_id != target_id, Error::<T>::IdenticalSwaps);
+
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let hops = Self::route_exact_in(&[swap_id, target_id], Asset::Token(swap.token_id), tokens_sold)?;
+		let tokens_bought = hops.last().map(|hop| hop.amount_out).unwrap_or_else(Zero::zero);
+
+		ensure!(tokens_bought >= min_tokens_bought, Error::<T>::NotEnoughTokens);
+
+		Self::execute_route(buyer, hops, recipient)
+	}
+
+	/// Buys exactly `tokens_bought` of the tokens in `target_id` with the base
+	/// asset obtained by selling the tokens in `swap_id`.
+	fn do_tokens_to_swap_output(
+		buyer: T::AccountId,
+		swap_id: T::SwapId,
+		tokens_bought: T::TokenBalance,
+		max_tokens_sold: T::TokenBalance,
+		deadline: T::BlockNumber,
+		recipient: T::AccountId,
+		target_id: T::SwapId,
+	) -> dispatch::DispatchResult
+	{
+		let now = system::Module::<T>::block_number();
+		ensure!(deadline >= now, Error::<T>::Deadline);
+
+		ensure!(tokens_bought > Zero::zero(), Error::<T>::NoTokensSwapped);
+		ensure!(max_tokens_sold > Zero::zero(), Error::<T>::NoTokensSwapped);
+		ensure!(swap_id != target_id, Error::<T>::IdenticalSwaps);
+
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let hops = Self::route_exact_out(&[swap_id, target_id], Asset::Token(swap.token_id), tokens_bought)?;
+		let tokens_sold = hops.first().map(|hop| hop.amount_in).unwrap_or_else(Zero::zero);
+
+		ensure!(max_tokens_sold >= tokens_sold, Error::<T>::TooExpensiveTokens);
+
+		Self::execute_route(buyer, hops, recipient)
+	}
+
+	/// Checks that `path` is non-empty, not too long and visits every swap at
+	/// most once, so that each hop can be quoted against untouched reserves.
+	fn ensure_valid_path(path: &[T::SwapId]) -> dispatch::DispatchResult {
+		ensure!(!path.is_empty(), Error::<T>::InvalidPath);
+		ensure!(path.len() <= MAX_PATH_LENGTH, Error::<T>::PathTooLong);
+		for (i, swap_id) in path.iter().enumerate() {
+			ensure!(!path[..i].contains(swap_id), Error::<T>::InvalidPath);
+		}
+		Ok(())
+	}
+
+	/// The part of `amount` of `asset_in` that `add_liquidity_single_sided`
+	/// sells to `swap`: the least for which what is left is worth no more
+	/// than what it buys at the reserves after the sale.
+	///
+	/// Found by bisection with the swap's own quotes, so it holds for every
+	/// curve that prices liquidity in shares. Constant product swaps start
+	/// from the closed form and only settle its rounding. Other curves stop
+	/// after `MAX_SINGLE_SIDED_STEPS` quotes on an amount that sells enough,
+	/// leaving a little more of the other asset with the caller.
+	fn single_sided_sale(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		let sells_enough = |sold: T::TokenBalance| -> Result<bool, DispatchError> {
+			// A sale too large to quote or apply counts as too much.
+			let bought = match Self::swap_input_price(swap_id, swap, asset_in, sold, input_reserve, output_reserve) {
+				Ok(bought) => bought,
+				Err(_) => return Ok(true),
+			};
+			let after = match Self::simulate_trade(swap_id, swap, asset_in, sold, bought, true) {
+				Ok((_, after, _)) => after,
+				Err(_) => return Ok(true),
+			};
+			let (input_after, output_after) = if asset_in == swap.base {
+				(after.base_reserve, after.token_reserve)
+			} else {
+				(after.token_reserve, after.base_reserve)
+			};
+			Ok(Self::to_u256(amount - sold)?.full_mul(Self::to_u256(output_after)?)
+				<= Self::to_u256(bought)?.full_mul(Self::to_u256(input_after)?))
+		};
+
+		let mut low: T::TokenBalance = One::one();
+		let mut high = amount;
+		let estimate = if Self::amplification(swap).is_none() && Self::weights(swap, asset_in).is_none() {
+			Self::constant_product_sale(swap, amount, input_reserve)
+		} else {
+			None
+		};
+		if let Some(estimate) = estimate {
+			// Bracket the estimate so the bisection below only settles rounding.
+			let estimate = Self::from_u256(estimate).unwrap_or(amount).max(low).min(high);
+			let below = estimate.saturating_sub(2u32.into()).max(low);
+			let above = estimate.saturating_add(2u32.into()).min(high);
+			if sells_enough(above)? {
+				high = above;
+			}
+			if below < high && !sells_enough(below)? {
+				low = below + One::one();
+			}
+		}
+
+		let mut steps = 0;
+		while low < high && steps < MAX_SINGLE_SIDED_STEPS {
+			let mid = low + (high - low) / 2u32.into();
+			if sells_enough(mid)? {
+				high = mid;
+			} else {
+				low = mid + One::one();
+			}
+			steps += 1;
+		}
+		Ok(high)
+	}
+
+	/// The sale `s` of `amount` for `single_sided_sale` on a constant product
+	/// swap, ignoring rounding.
+	///
+	/// With `R` the input reserve, `r` what is left of the input after the
+	/// fee and `k` what the reserve keeps of it after the protocol fee, `s`
+	/// solves `r k s^2 + R (1 + r) s - R amount = 0`. Both fractions are
+	/// scaled by `Permill` accuracy, which the solution divides back out.
+	fn constant_product_sale(swap: &SwapOf<T>, amount: T::TokenBalance, input_reserve: T::TokenBalance)
+		-> Option<U256>
+	{
+		let accuracy = Permill::one().deconstruct();
+		let fee = Self::swap_fee(swap).deconstruct();
+		let r = U256::from(accuracy - fee);
+		let k = U256::from(accuracy - T::ProtocolFee::get() * fee);
+		let reserve = Self::to_u256(input_reserve).ok()?;
+		let amount = Self::to_u256(amount).ok()?;
+
+		let b = reserve.checked_mul(U256::from(accuracy) + r)?;
+		let rk = r.full_mul(k);
+		let discriminant = b.full_mul(b)
+			.checked_add(rk.checked_mul(reserve.full_mul(amount))?.checked_mul(U512::from(4))?)?;
+		let sale = Self::sqrt(discriminant)
+			.checked_sub(U512::from(b))?
+			.checked_mul(U512::from(accuracy))?
+			.checked_div(rk * U512::from(2))?;
+		U256::try_from(sale).ok()
+	}
+
+	/// The integer square root of `value`, rounded down.
+	fn sqrt(value: U512) -> U512 {
+		if value.is_zero() {
+			return value;
+		}
+		// Newton's method from a power of two above the root only falls.
+		let mut root = U512::one() << ((value.bits() + 1) / 2);
+		loop {
+			let next = (root + value / root) >> 1;
+			if next >= root {
+				return root;
+			}
+			root = next;
+		}
+	}
+
+	/// Returns the asset received for `asset_in` from `swap`, along with the
+	/// reserves of the input and output side.
+	fn hop_side(swap: &SwapOf<T>, asset_in: Asset<T::TokenId>)
+		-> Result<(Asset<T::TokenId>, T::TokenBalance, T::TokenBalance), DispatchError>
+	{
+		let token_reserve = Self::get_token_reserve(swap);
+		let base_reserve = Self::get_base_reserve(swap);
+		if asset_in == swap.base {
+			Ok((Asset::Token(swap.token_id), base_reserve, token_reserve))
+		} else if asset_in == Asset::Token(swap.token_id) {
+			Ok((swap.base, token_reserve, base_reserve))
+		} else {
+			Err(Error::<T>::InvalidPath.into())
+		}
+	}
+
+	/// The asset on `side` of `swap`.
+	fn side_asset(swap: &SwapOf<T>, side: Side) -> Asset<T::TokenId> {
+		match side {
+			Side::Base => swap.base,
+			Side::Token => Asset::Token(swap.token_id),
+		}
+	}
+
+	/// Quotes every hop of `path` when selling exactly `amount_in` of `asset_in`.
+	fn route_exact_in(path: &[T::SwapId], asset_in: Asset<T::TokenId>, amount_in: T::TokenBalance)
+		-> Result<Vec<Hop<T>>, DispatchError>
+	{
+		Self::ensure_valid_path(path)?;
+
+		let mut hops = Vec::with_capacity(path.len());
+		let mut asset = asset_in;
+		let mut amount = amount_in;
+		for swap_id in path {
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let (asset_out, input_reserve, output_reserve) = Self::hop_side(&swap, asset)?;
+			let amount_out = Self::swap_input_price(*swap_id, &swap, asset, amount, input_reserve, output_reserve)?;
+			ensure!(amount_out > Zero::zero(), Error::<T>::NoLiquidity);
+
+			hops.push(Hop {
+				swap_id: *swap_id,
+				swap,
+				asset_in: asset,
+				asset_out,
+				amount_in: amount,
+				amount_out,
+				exact_in: true,
+			});
+			asset = asset_out;
+			amount = amount_out;
+		}
+
+		Ok(hops)
+	}
+
+	/// Quotes every hop of `path` when buying exactly `amount_out` of the final
+	/// asset with `asset_in`.
+	fn route_exact_out(path: &[T::SwapId], asset_in: Asset<T::TokenId>, amount_out: T::TokenBalance)
+		-> Result<Vec<Hop<T>>, DispatchError>
+	{
+		Self::ensure_valid_path(path)?;
+
+		// Walk forwards to find the assets, then backwards to find the amounts.
+		let mut sides = Vec::with_capacity(path.len());
+		let mut asset = asset_in;
+		for swap_id in path {
+			let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+			let (asset_out, input_reserve, output_reserve) = Self::hop_side(&swap, asset)?;
+			sides.push((*swap_id, swap, asset, asset_out, input_reserve, output_reserve));
+			asset = asset_out;
+		}
+
+		let mut hops = Vec::with_capacity(path.len());
+		let mut amount = amount_out;
+		for (swap_id, swap, asset_in, asset_out, input_reserve, output_reserve) in sides.into_iter().rev() {
+			let amount_in = Self::swap_output_price(swap_id, &swap, asset_in, amount, input_reserve, output_reserve)?;
+
+			hops.push(Hop {
+				swap_id,
+				swap,
+				asset_in,
+				asset_out,
+				amount_in,
+				amount_out: amount,
+				exact_in: false,
+			});
+			amount = amount_in;
+		}
+		hops.reverse();
+
+		Ok(hops)
+	}
+
+	/// Moves the funds of a quoted route. The buyer pays into the first swap
+	/// and every swap pays its output straight into the next one, with the
+	/// last paying the recipient.
+	///
+	/// Every hop is checked before the buyer pays in, so nothing after that
+	/// payment can fail and the route either completes or has no effect.
+	fn execute_route(buyer: T::AccountId, hops: Vec<Hop<T>>, recipient: T::AccountId)
+		-> dispatch::DispatchResult
+	{
+		let first = hops.first().ok_or(Error::<T>::InvalidPath)?;
+		let dests: Vec<T::AccountId> = hops.iter()
+			.skip(1)
+			.map(|next| next.swap.account.clone())
+			.chain(sp_std::iter::once(recipient))
+			.collect();
+		for (hop, dest) in hops.iter().zip(&dests) {
+			Self::ensure_trading_allowed(hop.swap_id)?;
+			Self::ensure_within_circuit_breaker(
+				hop.swap_id,
+				&hop.swap,
+				hop.asset_in,
+				hop.amount_in,
+				hop.amount_out,
+				hop.exact_in,
+			)?;
+			Self::ensure_can_pay_out(hop.asset_out, &hop.swap.account, dest, hop.amount_out)?;
+		}
+		for hop in &hops {
+			Self::update_price_oracle(hop.swap_id)?;
+		}
+		Self::transfer_asset(first.asset_in, &buyer, &first.swap.account, first.amount_in, ExistenceRequirement::KeepAlive)?;
+
+		for (hop, dest) in hops.iter().zip(&dests) {
+			Self::apply_trade(hop.swap_id, &hop.swap, hop.asset_in, hop.amount_in, hop.amount_out, hop.exact_in)?;
+			Self::transfer_asset(hop.asset_out, &hop.swap.account, dest, hop.amount_out, ExistenceRequirement::AllowDeath)?;
+			Self::record_trade(hop.swap_id, &hop.swap, &buyer, dest, hop.asset_in, hop.amount_in, hop.amount_out);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that `from` holds `amount` of `asset` and that `to` can receive
+	/// it, as `transfer_asset` would.
+	fn ensure_can_pay_out(
+		asset: Asset<T::TokenId>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::TokenBalance,
+	) -> dispatch::DispatchResult
+	{
+		ensure!(Self::asset_balance(asset, from)? >= amount, Error::<T>::InsufficientReserve);
+		match asset {
+			Asset::Currency => {
+				let received = T::Currency::total_balance(to)
+					.checked_add(&Self::unconvert(amount)?)
+					.ok_or(Error::<T>::Overflow)?;
+				ensure!(received >= T::Currency::minimum_balance(), Error::<T>::BelowExistentialDeposit);
+			}
+			Asset::Token(id) => {
+				fungible::Module::<T>::balance_of((id, to))
+					.checked_add(&amount)
+					.ok_or(Error::<T>::Overflow)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks that `who` can pay `amount` of `asset` and keep its account
+	/// alive, as `transfer_asset` would with `KeepAlive`.
+	fn ensure_can_pay_in(asset: Asset<T::TokenId>, who: &T::AccountId, amount: T::TokenBalance)
+		-> dispatch::DispatchResult
+	{
+		match asset {
+			Asset::Currency => {
+				let amount = Self::unconvert(amount)?;
+				let new_balance = T::Currency::free_balance(who)
+					.checked_sub(&amount)
+					.ok_or(Error::<T>::InsufficientBalance)?;
+				ensure!(new_balance >= T::Currency::minimum_balance(), Error::<T>::InsufficientBalance);
+				T::Currency::ensure_can_withdraw(who, amount, WithdrawReason::Transfer.into(), new_balance)?;
+			}
+			Asset::Token(id) => {
+				ensure!(fungible::Module::<T>::balance_of((id, who)) >= amount, Error::<T>::InsufficientBalance);
+			}
+		}
+		Ok(())
+	}
+
+	fn transfer_asset(
+		asset: Asset<T::TokenId>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::TokenBalance,
+		existence: ExistenceRequirement,
+	) -> dispatch::DispatchResult
+	{
+		match asset {
+			Asset::Currency => T::Currency::transfer(from, to, Self::unconvert(amount)?, existence),
+			Asset::Token(id) => fungible::Module::<T>::do_transfer(id, from.clone(), to.clone(), amount),
+		}
+	}
+
+	/// Quotes selling `input_amount` of `asset_in` to `swap` with the swap's
+	/// curve.
+	fn swap_input_price(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		input_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		let fee = Self::swap_fee(swap);
+		if Self::tick_spacing(swap).is_some() {
+			let trade = Self::simulate_concentrated_trade(swap_id, swap, asset_in, input_amount, true)?;
+			Ok(Self::from_u256(trade.amount_out)?)
+		} else if let Some(a) = Self::amplification(swap) {
+			Self::get_stable_input_price(input_amount, input_reserve, output_reserve, a, fee)
+		} else if let Some(weights) = Self::weights(swap, asset_in) {
+			Self::get_weighted_input_price(input_amount, input_reserve, output_reserve, weights, fee)
+		} else {
+			Self::get_input_price(input_amount, input_reserve, output_reserve, fee)
+		}
+	}
+
+	/// Quotes buying `output_amount` from `swap` with `asset_in` with the
+	/// swap's curve.
+	fn swap_output_price(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		output_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		let fee = Self::swap_fee(swap);
+		if Self::tick_spacing(swap).is_some() {
+			let trade = Self::simulate_concentrated_trade(swap_id, swap, asset_in, output_amount, false)?;
+			Ok(Self::from_u256(trade.amount_in)?)
+		} else if let Some(a) = Self::amplification(swap) {
+			Self::get_stable_output_price(output_amount, input_reserve, output_reserve, a, fee)
+		} else if let Some(weights) = Self::weights(swap, asset_in) {
+			Self::get_weighted_output_price(output_amount, input_reserve, output_reserve, weights, fee)
+		} else {
+			Self::get_output_price(output_amount, input_reserve, output_reserve, fee)
+		}
+	}
+
+	fn get_output_price(
+		output_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+		ensure!(output_amount < output_reserve, Error::<T>::InsufficientReserve);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let numerator = Self::to_u256(input_reserve)?
+			.checked_mul(Self::to_u256(output_amount)?)
+			.and_then(|n| n.checked_mul(accuracy))
+			.ok_or(Error::<T>::Overflow)?;
+		let denominator = Self::to_u256(output_reserve - output_amount)?
+			.checked_mul(fee_complement)
+			.ok_or(Error::<T>::Overflow)?;
+		let input_amount = numerator.checked_div(denominator)
+			.ok_or(Error::<T>::InvalidFee)?
+			.checked_add(U256::one())
+			.ok_or(Error::<T>::Overflow)?;
+		Ok(Self::from_u256(input_amount)?)
+	}
+
+	fn get_input_price(
+		input_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let input_amount_with_fee = Self::to_u256(input_amount)?
+			.checked_mul(fee_complement)
+			.ok_or(Error::<T>::Overflow)?;
+		let numerator = input_amount_with_fee
+			.checked_mul(Self::to_u256(output_reserve)?)
+			.ok_or(Error::<T>::Overflow)?;
+		let denominator = Self::to_u256(input_reserve)?
+			.checked_mul(accuracy)
+			.and_then(|d| d.checked_add(input_amount_with_fee))
+			.ok_or(Error::<T>::Overflow)?;
+		Ok(Self::from_u256(numerator / denominator)?)
+	}
+
+	fn get_stable_output_price(
+		output_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		amplification: u32,
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+		ensure!(output_amount < output_reserve, Error::<T>::InsufficientReserve);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let input_reserve = Self::to_u256(input_reserve)?;
+		let output_reserve = Self::to_u256(output_reserve)?;
+		let d = stable_swap::invariant(input_reserve, output_reserve, amplification)
+			.ok_or(Error::<T>::InvariantNotFound)?;
+		let new_output_reserve = output_reserve - Self::to_u256(output_amount)?;
+		let new_input_reserve = stable_swap::other_reserve(new_output_reserve, d, amplification)
+			.ok_or(Error::<T>::InvariantNotFound)?;
+
+		// Rounds up in favour of the swap, both before and after the fee.
+		let input_amount = new_input_reserve.saturating_sub(input_reserve)
+			.checked_add(U256::one())
+			.and_then(|amount| amount.checked_mul(accuracy))
+			.ok_or(Error::<T>::Overflow)?
+			.checked_div(fee_complement)
+			.ok_or(Error::<T>::InvalidFee)?
+			.checked_add(U256::one())
+			.ok_or(Error::<T>::Overflow)?;
+		Ok(Self::from_u256(input_amount)?)
+	}
+
+	fn get_stable_input_price(
+		input_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		amplification: u32,
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let input_reserve = Self::to_u256(input_reserve)?;
+		let output_reserve = Self::to_u256(output_reserve)?;
+		let input_amount_with_fee = Self::to_u256(input_amount)?
+			.checked_mul(fee_complement)
+			.ok_or(Error::<T>::Overflow)?
+			/ accuracy;
+		let d = stable_swap::invariant(input_reserve, output_reserve, amplification)
+			.ok_or(Error::<T>::InvariantNotFound)?;
+		let new_input_reserve = input_reserve.checked_add(input_amount_with_fee).ok_or(Error::<T>::Overflow)?;
+		let new_output_reserve = stable_swap::other_reserve(new_input_reserve, d, amplification)
+			.ok_or(Error::<T>::InvariantNotFound)?;
+
+		// Rounds down in favour of the swap.
+		let output_amount = output_reserve.saturating_sub(new_output_reserve).saturating_sub(U256::one());
+		Ok(Self::from_u256(output_amount)?)
+	}
+
+	fn get_weighted_output_price(
+		output_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		(input_weight, output_weight): (u32, u32),
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+
+		// Keeps the power approximation within its range, as Balancer does.
+		let output_amount = Self::to_u256(output_amount)?;
+		let output_reserve = Self::to_u256(output_reserve)?;
+		ensure!(output_amount <= output_reserve / 3, Error::<T>::TradeTooLarge);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let input_amount = weighted::input_amount(
+			Self::to_u256(input_reserve)?,
+			input_weight,
+			output_reserve,
+			output_weight,
+			output_amount,
+		).ok_or(Error::<T>::Overflow)?;
+		let input_amount = input_amount.checked_mul(accuracy)
+			.ok_or(Error::<T>::Overflow)?
+			.checked_div(fee_complement)
+			.ok_or(Error::<T>::InvalidFee)?
+			.checked_add(U256::one())
+			.ok_or(Error::<T>::Overflow)?;
+		Ok(Self::from_u256(input_amount)?)
+	}
+
+	fn get_weighted_input_price(
+		input_amount: T::TokenBalance,
+		input_reserve: T::TokenBalance,
+		output_reserve: T::TokenBalance,
+		(input_weight, output_weight): (u32, u32),
+		fee: Permill,
+	) -> Result<T::TokenBalance, DispatchError>
+	{
+		ensure!(!input_reserve.is_zero() && !output_reserve.is_zero(), Error::<T>::NoLiquidity);
+
+		// Keeps the power approximation within its range, as Balancer does.
+		let input_amount = Self::to_u256(input_amount)?;
+		let input_reserve = Self::to_u256(input_reserve)?;
+		ensure!(input_amount <= input_reserve / 2, Error::<T>::TradeTooLarge);
+
+		let (accuracy, fee_complement) = Self::fee_factors(fee);
+		let input_amount_with_fee = input_amount.checked_mul(fee_complement)
+			.ok_or(Error::<T>::Overflow)?
+			/ accuracy;
+		let output_amount = weighted::output_amount(
+			input_reserve,
+			input_weight,
+			Self::to_u256(output_reserve)?,
+			output_weight,
+			input_amount_with_fee,
+		).ok_or(Error::<T>::Overflow)?;
+		Ok(Self::from_u256(output_amount)?)
+	}
+
+	/// Walks a trade through the ranges of a concentrated liquidity swap
+	/// without changing any state. `amount` is the input, including the fee,
+	/// when `exact_in` and the output otherwise.
+	fn simulate_concentrated_trade(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount: T::TokenBalance,
+		exact_in: bool,
+	) -> Result<ConcentratedTrade, DispatchError>
+	{
+		let tick_spacing = Self::tick_spacing(swap).ok_or(Error::<T>::NotConcentratedSwap)?;
+		let mut pool = Self::concentrated_pool(swap_id).ok_or(Error::<T>::NotConcentratedSwap)?;
+		let fee = Self::swap_fee(swap).deconstruct();
+		let protocol_fee = T::ProtocolFee::get().deconstruct();
+		let million = U256::from(Permill::one().deconstruct());
+
+		// Selling the base asset moves the price down towards the lowest tick.
+		let base_in = asset_in == swap.base;
+		let sqrt_price_limit = if base_in {
+			concentrated::min_sqrt_price() + U256::one()
+		} else {
+			concentrated::max_sqrt_price() - U256::one()
+		};
+
+		let mut remaining = Self::to_u256(amount)?;
+		let mut calculated = U256::zero();
+		let mut protocol_total = U256::zero();
+		let mut crossed = Vec::new();
+		let mut steps = 0;
+		while !remaining.is_zero() && pool.sqrt_price != sqrt_price_limit {
+			steps += 1;
+			ensure!(steps <= MAX_SWAP_STEPS, Error::<T>::TooManySwapSteps);
+
+			let (tick_next, initialized) = Self::next_initialized_tick(swap_id, pool.tick, tick_spacing, base_in);
+			let tick_next = tick_next.max(concentrated::MIN_TICK).min(concentrated::MAX_TICK);
+			let sqrt_price_next = concentrated::sqrt_price_at_tick(tick_next).ok_or(Error::<T>::Overflow)?;
+			let target = if base_in {
+				sqrt_price_next.max(sqrt_price_limit)
+			} else {
+				sqrt_price_next.min(sqrt_price_limit)
+			};
+
+			let step = concentrated::swap_step(pool.sqrt_price, target, pool.liquidity, remaining, exact_in, fee)
+				.ok_or(Error::<T>::Overflow)?;
+			let paid = step.amount_in.checked_add(step.fee_amount).ok_or(Error::<T>::Overflow)?;
+			if exact_in {
+				remaining = remaining.saturating_sub(paid);
+				calculated = calculated.checked_add(step.amount_out).ok_or(Error::<T>::Overflow)?;
+			} else {
+				remaining = remaining.saturating_sub(step.amount_out);
+				calculated = calculated.checked_add(paid).ok_or(Error::<T>::Overflow)?;
+			}
+
+			// The liquidity in range earns the fee less the protocol's share.
+			let step_protocol_fee = step.fee_amount * U256::from(protocol_fee) / million;
+			protocol_total = protocol_total.checked_add(step_protocol_fee).ok_or(Error::<T>::Overflow)?;
+			if pool.liquidity > 0 {
+				let growth = concentrated::mul_div(
+					step.fee_amount - step_protocol_fee,
+					U256::one() << 128,
+					U256::from(pool.liquidity),
+				).ok_or(Error::<T>::Overflow)?;
+				if base_in {
+					pool.fee_growth_base = pool.fee_growth_base.overflowing_add(growth).0;
+				} else {
+					pool.fee_growth_tokens = pool.fee_growth_tokens.overflowing_add(growth).0;
+				}
+			}
+
+			if step.sqrt_price == sqrt_price_next {
+				if initialized {
+					crossed.push((tick_next, pool.fee_growth_base, pool.fee_growth_tokens));
+					let liquidity_net = Self::ticks((swap_id, tick_next)).unwrap_or_default().liquidity_net;
+					let liquidity_net = if base_in { -liquidity_net } else { liquidity_net };
+					pool.liquidity = concentrated::add_liquidity_delta(pool.liquidity, liquidity_net)
+						.ok_or(Error::<T>::Overflow)?;
+				}
+				pool.tick = if base_in { tick_next - 1 } else { tick_next };
+			} else if step.sqrt_price != pool.sqrt_price {
+				pool.tick = concentrated::tick_at_sqrt_price(step.sqrt_price).ok_or(Error::<T>::InvalidPrice)?;
+			}
+			pool.sqrt_price = step.sqrt_price;
+		}
+		ensure!(remaining.is_zero(), Error::<T>::InsufficientLiquidity);
+
+		let specified = Self::to_u256(amount)?;
+		let (amount_in, amount_out) = if exact_in { (specified, calculated) } else { (calculated, specified) };
+		Ok(ConcentratedTrade { amount_in, amount_out, protocol_fee: protocol_total, pool, crossed })
+	}
+
+	/// Applies a quoted trade to the reserves of a swap, sets aside the
+	/// protocol fee and, for concentrated liquidity, moves its price.
+	///
+	/// Must run after the input has been paid in and before the output is
+	/// paid out, in the same state the trade was quoted in.
+	fn apply_trade(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount_in: T::TokenBalance,
+		amount_out: T::TokenBalance,
+		exact_in: bool,
+	) -> dispatch::DispatchResult
+	{
+		Self::update_reference_price(swap_id, swap)?;
+		let (protocol_fee, _, trade) = Self::simulate_trade(swap_id, swap, asset_in, amount_in, amount_out, exact_in)?;
+		if let Some(trade) = trade {
+			for (tick, fee_growth_base, fee_growth_tokens) in trade.crossed {
+				<Ticks<T>>::mutate((swap_id, tick), |maybe_tick| {
+					if let Some(info) = maybe_tick {
+						info.fee_growth_outside_base = fee_growth_base.overflowing_sub(info.fee_growth_outside_base).0;
+						info.fee_growth_outside_tokens = fee_growth_tokens.overflowing_sub(info.fee_growth_outside_tokens).0;
+					}
+				});
+			}
+			<ConcentratedPools<T>>::insert(swap_id, trade.pool);
+		}
+		Self::set_aside_protocol_fee(swap_id, asset_in, protocol_fee);
+
+		let kept = amount_in.saturating_sub(protocol_fee);
+		if asset_in == swap.base {
+			Self::update_reserves(swap_id, kept, Zero::zero(), Zero::zero(), amount_out)
+		} else {
+			Self::update_reserves(swap_id, Zero::zero(), kept, amount_out, Zero::zero())
+		}
+	}
+
+	/// The protocol fee `apply_trade` sets aside for a quoted trade and the
+	/// reserves it leaves `swap` with, along with the concentrated liquidity
+	/// trade it makes on those swaps.
+	fn simulate_trade(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount_in: T::TokenBalance,
+		amount_out: T::TokenBalance,
+		exact_in: bool,
+	) -> Result<(T::TokenBalance, SwapOf<T>, Option<ConcentratedTrade>), DispatchError>
+	{
+		let (protocol_fee, trade) = if Self::tick_spacing(swap).is_some() {
+			let amount = if exact_in { amount_in } else { amount_out };
+			let trade = Self::simulate_concentrated_trade(swap_id, swap, asset_in, amount, exact_in)?;
+			(Self::from_u256(trade.protocol_fee)?, Some(trade))
+		} else {
+			(T::ProtocolFee::get() * (Self::swap_fee(swap) * amount_in), None)
+		};
+
+		let kept = amount_in.saturating_sub(protocol_fee);
+		let mut after = swap.clone();
+		let (input_reserve, output_reserve) = if asset_in == swap.base {
+			(&mut after.base_reserve, &mut after.token_reserve)
+		} else {
+			(&mut after.token_reserve, &mut after.base_reserve)
+		};
+		*input_reserve = input_reserve.checked_add(&kept).ok_or(Error::<T>::Overflow)?;
+		*output_reserve = output_reserve.checked_sub(&amount_out).ok_or(Error::<T>::InsufficientReserve)?;
+
+		Ok((protocol_fee, after, trade))
+	}
+
+	/// The next initialised tick at or below `tick` when `lte`, or above it
+	/// otherwise, looking no further than the bitmap word the search starts
+	/// in. Returns the edge of the word and `false` if there is none.
+	fn next_initialized_tick(swap_id: T::SwapId, tick: i32, tick_spacing: i32, lte: bool) -> (i32, bool) {
+		let compressed = concentrated::compress(tick, tick_spacing);
+		if lte {
+			let (word, bit) = concentrated::bitmap_position(compressed);
+			match concentrated::highest_bit_at_or_below(Self::tick_bitmap((swap_id, word)), bit) {
+				Some(next) => ((compressed - i32::from(bit - next)) * tick_spacing, true),
+				None => ((compressed - i32::from(bit)) * tick_spacing, false),
+			}
+		} else {
+			let (word, bit) = concentrated::bitmap_position(compressed + 1);
+			match concentrated::lowest_bit_at_or_above(Self::tick_bitmap((swap_id, word)), bit) {
+				Some(next) => ((compressed + 1 + i32::from(next - bit)) * tick_spacing, true),
+				None => ((compressed + 1 + i32::from(255 - bit)) * tick_spacing, false),
+			}
+		}
+	}
+
+	/// Checks that a position's ticks are ordered multiples of the tick spacing
+	/// within the range of ticks.
+	fn ensure_valid_range(tick_lower: i32, tick_upper: i32, tick_spacing: i32) -> dispatch::DispatchResult {
+		ensure!(
+			tick_lower < tick_upper
+				&& tick_lower >= concentrated::MIN_TICK
+				&& tick_upper <= concentrated::MAX_TICK
+				&& tick_lower % tick_spacing == 0
+				&& tick_upper % tick_spacing == 0,
+			Error::<T>::InvalidTickRange
+		);
+		Ok(())
+	}
+
+	/// The base asset and tokens held by `liquidity` between two ticks at the
+	/// current price of `pool`.
+	fn position_amounts(
+		pool: &ConcentratedPool,
+		tick_lower: i32,
+		tick_upper: i32,
+		liquidity: u128,
+		round_up: bool,
+	) -> Result<(T::TokenBalance, T::TokenBalance), DispatchError>
+	{
+		let sqrt_price_lower = concentrated::sqrt_price_at_tick(tick_lower).ok_or(Error::<T>::InvalidTickRange)?;
+		let sqrt_price_upper = concentrated::sqrt_price_at_tick(tick_upper).ok_or(Error::<T>::InvalidTickRange)?;
+
+		// Below the range the liquidity is all in the base asset and above it
+		// all in tokens.
+		let (base_from, token_to) = if pool.tick < tick_lower {
+			(sqrt_price_lower, sqrt_price_lower)
+		} else if pool.tick < tick_upper {
+			(pool.sqrt_price, pool.sqrt_price)
+		} else {
+			(sqrt_price_upper, sqrt_price_upper)
+		};
+		let base = concentrated::base_delta(base_from, sqrt_price_upper, liquidity, round_up)
+			.ok_or(Error::<T>::Overflow)?;
+		let tokens = concentrated::token_delta(sqrt_price_lower, token_to, liquidity, round_up)
+			.ok_or(Error::<T>::Overflow)?;
+		Ok((Self::from_u256(base)?, Self::from_u256(tokens)?))
+	}
+
+	/// A tick of a swap after `liquidity_delta` is added to a position it is
+	/// the lower or upper bound of.
+	fn updated_tick(
+		swap_id: T::SwapId,
+		pool: &ConcentratedPool,
+		tick: i32,
+		liquidity_delta: i128,
+		upper: bool,
+	) -> Result<Tick, DispatchError>
+	{
+		let mut info = Self::ticks((swap_id, tick)).unwrap_or_default();
+		// By convention, all growth before a tick is initialised happened
+		// below it.
+		if info.liquidity_gross == 0 && tick <= pool.tick {
+			info.fee_growth_outside_base = pool.fee_growth_base;
+			info.fee_growth_outside_tokens = pool.fee_growth_tokens;
+		}
+		info.liquidity_gross = concentrated::add_liquidity_delta(info.liquidity_gross, liquidity_delta)
+			.ok_or(Error::<T>::Overflow)?;
+		info.liquidity_net = if upper {
+			info.liquidity_net.checked_sub(liquidity_delta)
+		} else {
+			info.liquidity_net.checked_add(liquidity_delta)
+		}.ok_or(Error::<T>::Overflow)?;
+		Ok(info)
+	}
+
+	/// Stores a tick, removing it and flipping its bit in the bitmap once no
+	/// position uses it.
+	fn write_tick(swap_id: T::SwapId, tick: i32, info: Tick, tick_spacing: i32) {
+		let was_initialized = <Ticks<T>>::contains_key((swap_id, tick));
+		let is_initialized = info.liquidity_gross > 0;
+		if was_initialized != is_initialized {
+			let (word, bit) = concentrated::bitmap_position(tick / tick_spacing);
+			<TickBitmap<T>>::mutate((swap_id, word), |bits| *bits = *bits ^ (U256::one() << bit as usize));
+		}
+
+		if is_initialized {
+			<Ticks<T>>::insert((swap_id, tick), info);
+		} else {
+			<Ticks<T>>::remove((swap_id, tick));
+		}
+	}
+
+	/// The fees earned per unit of liquidity between two ticks, as
+	/// (base asset, tokens).
+	fn fee_growth_inside(swap_id: T::SwapId, pool: &ConcentratedPool, tick_lower: i32, tick_upper: i32)
+		-> (U256, U256)
+	{
+		let lower = Self::ticks((swap_id, tick_lower)).unwrap_or_default();
+		let upper = Self::ticks((swap_id, tick_upper)).unwrap_or_default();
+		let inside = |global: U256, lower_outside: U256, upper_outside: U256| -> U256 {
+			let below = if pool.tick >= tick_lower { lower_outside } else { global.overflowing_sub(lower_outside).0 };
+			let above = if pool.tick < tick_upper { upper_outside } else { global.overflowing_sub(upper_outside).0 };
+			global.overflowing_sub(below).0.overflowing_sub(above).0
+		};
+		(
+			inside(pool.fee_growth_base, lower.fee_growth_outside_base, upper.fee_growth_outside_base),
+			inside(pool.fee_growth_tokens, lower.fee_growth_outside_tokens, upper.fee_growth_outside_tokens),
+		)
+	}
+
+	/// Adds the fees a position earned since it was last settled to what it
+	/// is owed.
+	fn settle_position_fees(position: &mut PositionOf<T>, (inside_base, inside_tokens): (U256, U256))
+		-> Result<(), Error<T>>
+	{
+		let earned = |inside: U256, last: U256| -> Result<T::TokenBalance, Error<T>> {
+			let growth = inside.overflowing_sub(last).0;
+			let amount = concentrated::mul_div(growth, U256::from(position.liquidity), U256::one() << 128)
+				.ok_or(Error::<T>::Overflow)?;
+			Self::from_u256(amount)
+		};
+		let base = earned(inside_base, position.fee_growth_inside_base)?;
+		let tokens = earned(inside_tokens, position.fee_growth_inside_tokens)?;
+
+		position.base_owed = position.base_owed.saturating_add(base);
+		position.tokens_owed = position.tokens_owed.saturating_add(tokens);
+		position.fee_growth_inside_base = inside_base;
+		position.fee_growth_inside_tokens = inside_tokens;
+		Ok(())
+	}
+
+	/// Shares the rewards of the blocks since a farm was last updated between
+	/// its staked shares. Rewards of blocks without any stake go unclaimed.
+	fn update_farm(farm: &mut FarmOf<T>) -> Result<(), Error<T>> {
+		let until = system::Module::<T>::block_number().min(farm.end);
+		if until <= farm.reward_updated {
+			return Ok(());
+		}
+
+		if !farm.total_staked.is_zero() {
+			let blocks = U256::from((until - farm.reward_updated).saturated_into::<u128>());
+			let rewards = blocks.checked_mul(Self::to_u256(farm.reward_per_block)?).ok_or(Error::<T>::Overflow)?;
+			let growth = concentrated::mul_div(rewards, U256::one() << 128, Self::to_u256(farm.total_staked)?)
+				.ok_or(Error::<T>::Overflow)?;
+			farm.reward_per_share = farm.reward_per_share.overflowing_add(growth).0;
+		}
+		farm.reward_updated = until;
+		Ok(())
+	}
+
+	/// Adds the rewards a stake earned since it was last settled to what it
+	/// is owed.
+	fn settle_stake(farm: &FarmOf<T>, stake: &mut StakeOf<T>) -> Result<(), Error<T>> {
+		let growth = farm.reward_per_share.overflowing_sub(stake.reward_per_share).0;
+		let earned = concentrated::mul_div(growth, Self::to_u256(stake.amount)?, U256::one() << 128)
+			.ok_or(Error::<T>::Overflow)?;
+		stake.rewards_owed = stake.rewards_owed.saturating_add(Self::from_u256(earned)?);
+		stake.reward_per_share = farm.reward_per_share;
+		Ok(())
+	}
+
+	/// Stores a stake, or removes it once it holds nothing.
+	fn write_stake(swap_id: T::SwapId, who: &T::AccountId, stake: StakeOf<T>) {
+		if stake.amount.is_zero() && stake.rewards_owed.is_zero() {
+			<Stakes<T>>::remove((swap_id, who.clone()));
+		} else {
+			<Stakes<T>>::insert((swap_id, who.clone()), stake);
+		}
+	}
+
+	/// The fee accuracy and the share of an input left after the fee.
+	fn fee_factors(fee: Permill) -> (U256, U256) {
+		let accuracy = Permill::one().deconstruct();
+		(U256::from(accuracy), U256::from(accuracy.saturating_sub(fee.deconstruct())))
+	}
+
+	/// Computes `a * b / c` without overflowing in the intermediate product.
+	fn mul_div(a: T::TokenBalance, b: T::TokenBalance, c: T::TokenBalance)
+		-> Result<T::TokenBalance, Error<T>>
+	{
+		ensure!(!c.is_zero(), Error::<T>::NoLiquidity);
+		let product = Self::to_u256(a)?.checked_mul(Self::to_u256(b)?).ok_or(Error::<T>::Overflow)?;
+		Self::from_u256(product / Self::to_u256(c)?)
+	}
+
+	fn to_u256(value: T::TokenBalance) -> Result<U256, Error<T>> {
+		let value: u128 = value.try_into().map_err(|_| Error::<T>::Overflow)?;
+		Ok(U256::from(value))
+	}
+
+	fn from_u256(value: U256) -> Result<T::TokenBalance, Error<T>> {
+		ensure!(value <= U256::from(u128::max_value()), Error::<T>::Overflow);
+		T::TokenBalance::try_from(value.low_u128()).map_err(|_| Error::<T>::Overflow)
+	}
+
+	/// Logs a trade against `swap_id` along with the reserves left after it.
+	fn record_trade(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		buyer: &T::AccountId,
+		recipient: &T::AccountId,
+		asset_in: Asset<T::TokenId>,
+		amount_in: T::TokenBalance,
+		amount_out: T::TokenBalance,
+	) {
+		let fee = Self::swap_fee(swap) * amount_in;
+		let (base_reserve, token_reserve) = Self::get_reserves(swap_id).unwrap_or_default();
+		let (buyer, recipient) = (buyer.clone(), recipient.clone());
+		let event = match (swap.base == Asset::Currency, asset_in == swap.base) {
+			(true, true) => RawEvent::TokenPurchase(
+				swap_id,
+				buyer,
+				Self::currency_amount(amount_in),
+				amount_out,
+				Self::currency_amount(fee),
+				recipient,
+				Self::currency_amount(base_reserve),
+				token_reserve,
+			),
+			(true, false) => RawEvent::CurrencyPurchase(
+				swap_id,
+				buyer,
+				amount_in,
+				Self::currency_amount(amount_out),
+				fee,
+				recipient,
+				Self::currency_amount(base_reserve),
+				token_reserve,
+			),
+			(false, true) => RawEvent::PairTokenPurchase(
+				swap_id, buyer, amount_in, amount_out, fee, recipient, base_reserve, token_reserve,
+			),
+			(false, false) => RawEvent::PairBasePurchase(
+				swap_id, buyer, amount_in, amount_out, fee, recipient, base_reserve, token_reserve,
+			),
+		};
+		Self::deposit_event(event);
+	}
+
+	/// Logs liquidity added to a swap, with the base asset as currency for
+	/// swaps against the currency.
+	fn deposit_liquidity_added(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		who: T::AccountId,
+		base_amount: T::TokenBalance,
+		token_amount: T::TokenBalance,
+	) {
+		let event = if swap.base == Asset::Currency {
+			RawEvent::LiquidityAdded(swap_id, who, Self::currency_amount(base_amount), token_amount)
+		} else {
+			RawEvent::PairLiquidityAdded(swap_id, who, base_amount, token_amount)
+		};
+		Self::deposit_event(event);
+	}
+
+	/// Logs liquidity removed from a swap, with the base asset as currency
+	/// for swaps against the currency.
+	fn deposit_liquidity_removed(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		who: T::AccountId,
+		base_amount: T::TokenBalance,
+		token_amount: T::TokenBalance,
+	) {
+		let event = if swap.base == Asset::Currency {
+			RawEvent::LiquidityRemoved(swap_id, who, Self::currency_amount(base_amount), token_amount)
+		} else {
+			RawEvent::PairLiquidityRemoved(swap_id, who, base_amount, token_amount)
+		};
+		Self::deposit_event(event);
+	}
+
+	/// A base asset amount of a swap against the currency as a currency
+	/// balance, for its events. Such amounts were converted from currency, so
+	/// they convert back.
+	fn currency_amount(amount: T::TokenBalance) -> BalanceOf<T> {
+		Self::unconvert(amount).unwrap_or_else(|_| Bounded::max_value())
+	}
+
+	/// Sets aside the protocol's share of the fee paid on `amount_in` of
+	/// `asset_in`. The fees stay in the swap account but no longer count
+	/// towards its reserves until claimed.
+	fn accrue_protocol_fee(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount_in: T::TokenBalance,
+	) {
+		let protocol_fee = T::ProtocolFee::get() * (Self::swap_fee(swap) * amount_in);
+		Self::set_aside_protocol_fee(swap_id, asset_in, protocol_fee);
+	}
+
+	/// Stops counting `protocol_fee` of `asset_in` towards the reserves of a
+	/// swap until it is claimed.
+	fn set_aside_protocol_fee(swap_id: T::SwapId, asset_in: Asset<T::TokenId>, protocol_fee: T::TokenBalance) {
+		if protocol_fee.is_zero() {
+			return;
+		}
+
+		<Swaps<T>>::mutate(swap_id, |maybe_swap| {
+			if let Some(swap) = maybe_swap {
+				if asset_in == swap.base {
+					swap.protocol_base = swap.protocol_base.saturating_add(protocol_fee);
+				} else {
+					swap.protocol_tokens = swap.protocol_tokens.saturating_add(protocol_fee);
+				}
+			}
+		});
+
+		Self::deposit_event(RawEvent::ProtocolFeeAccrued(swap_id, asset_in, protocol_fee));
+	}
+
+	/// Checks that a swap was paid back for a flash swap of `base_out`
+	/// and `tokens_out` and returns the amounts paid in.
+	fn flash_swap_repayment(
+		swap_id: T::SwapId,
+		base_before: T::TokenBalance,
+		tokens_before: T::TokenBalance,
+		base_out: T::TokenBalance,
+		tokens_out: T::TokenBalance,
+	) -> Result<(T::TokenBalance, T::TokenBalance), DispatchError>
+	{
+		let swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let (base_after, tokens_after) = Self::held_balances(&swap)?;
+		let base_in = base_after.saturating_sub(base_before - base_out);
+		let tokens_in = tokens_after.saturating_sub(tokens_before - tokens_out);
+		ensure!(!base_in.is_zero() || !tokens_in.is_zero(), Error::<T>::FlashSwapNotRepaid);
+
+		// Mirrors Uniswap v2: the fee is only charged on the amounts paid in.
+		let (accuracy, _) = Self::fee_factors(Self::swap_fee(&swap));
+		let fee = U256::from(Self::swap_fee(&swap).deconstruct());
+		let adjusted = |after: T::TokenBalance, paid_in: T::TokenBalance| -> Result<U256, Error<T>> {
+			Self::to_u256(after)?
+				.checked_mul(accuracy)
+				.and_then(|after| after.checked_sub(Self::to_u256(paid_in).ok()?.checked_mul(fee)?))
+				.ok_or(Error::<T>::Overflow)
+		};
+		let base_adjusted = adjusted(base_after, base_in)?;
+		let tokens_adjusted = adjusted(tokens_after, tokens_in)?;
+		let repaid = if let Some(a) = Self::amplification(&swap) {
+			let invariant_after = stable_swap::invariant(base_adjusted / accuracy, tokens_adjusted / accuracy, a)
+				.ok_or(Error::<T>::InvariantNotFound)?;
+			let invariant_before = stable_swap::invariant(Self::to_u256(base_before)?, Self::to_u256(tokens_before)?, a)
+				.ok_or(Error::<T>::InvariantNotFound)?;
+			invariant_after >= invariant_before
+		} else if let Some((base_weight, token_weight)) = Self::weights(&swap, swap.base) {
+			weighted::invariant_holds(
+				Self::to_u256(base_before)?,
+				base_weight,
+				Self::to_u256(tokens_before)?,
+				token_weight,
+				base_adjusted / accuracy,
+				tokens_adjusted / accuracy,
+			).ok_or(Error::<T>::Overflow)?
+		} else {
+			let product_after = base_adjusted.full_mul(tokens_adjusted);
+			let product_before = Self::to_u256(base_before)?
+				.full_mul(Self::to_u256(tokens_before)?)
+				.checked_mul(accuracy.full_mul(accuracy))
+				.ok_or(Error::<T>::Overflow)?;
+			product_after >= product_before
+		};
+		ensure!(repaid, Error::<T>::FlashSwapNotRepaid);
+
+		// Repaying in the other asset is a trade, so it is held to the circuit
+		// breaker like any other.
+		let mut after = swap.clone();
+		after.base_reserve = base_after;
+		after.token_reserve = tokens_after;
+		Self::ensure_price_within_circuit_breaker(swap_id, &swap, &after, None)?;
+
+		Ok((base_in, tokens_in))
+	}
+
+	/// Pays the borrowed amounts of a flash swap out to `borrower`.
+	fn lend_flash_swap(
+		swap: &SwapOf<T>,
+		borrower: &T::AccountId,
+		base: T::TokenBalance,
+		tokens: T::TokenBalance,
+	) -> dispatch::DispatchResult
+	{
+		if !base.is_zero() {
+			Self::transfer_asset(swap.base, &swap.account, borrower, base, ExistenceRequirement::AllowDeath)?;
+		}
+		if !tokens.is_zero() {
+			fungible::Module::<T>::do_transfer(swap.token_id, swap.account.clone(), borrower.clone(), tokens)?;
+		}
+		Ok(())
+	}
+
+	/// Puts a swap's balances back to `held_before` after a failed flash
+	/// swap, taking what it is short of from `borrower` as far as they hold
+	/// it and refunding anything they paid in beyond it.
+	///
+	/// Nothing before this is rolled back, so it cannot fail.
+	fn unwind_flash_swap(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		borrower: &T::AccountId,
+		held_before: (T::TokenBalance, T::TokenBalance),
+	) {
+		let assets = [(swap.base, held_before.0), (Asset::Token(swap.token_id), held_before.1)];
+		for &(asset, before) in assets.iter() {
+			let held = Self::asset_balance(asset, &swap.account).unwrap_or(before);
+			if held < before {
+				Self::take_asset(asset, borrower, &swap.account, before - held);
+			} else if held > before {
+				Self::take_asset(asset, &swap.account, borrower, held - before);
+			}
+		}
+
+		// Whatever the borrower could not pay back is lost to the swap, so its
+		// reserves stop counting it.
+		if let Ok((base_held, tokens_held)) = Self::held_balances(swap) {
+			<Swaps<T>>::mutate(swap_id, |maybe_swap| {
+				if let Some(swap) = maybe_swap {
+					swap.base_reserve = swap.base_reserve.min(base_held);
+					swap.token_reserve = swap.token_reserve.min(tokens_held);
+				}
+			});
+		}
+	}
+
+	/// Moves `amount` of `asset` from `from` to `to`, or as much of it as
+	/// `from` holds. Unlike `transfer_asset` this cannot fail.
+	fn take_asset(asset: Asset<T::TokenId>, from: &T::AccountId, to: &T::AccountId, amount: T::TokenBalance) {
+		match asset {
+			Asset::Currency => {
+				let free = T::Currency::free_balance(from);
+				let amount = T::BalanceConvert::to_balance(amount).map_or(free, |amount| amount.min(free));
+				let (taken, _) = T::Currency::slash(from, amount);
+				T::Currency::resolve_creating(to, taken);
+			}
+			Asset::Token(id) => {
+				let amount = amount.min(fungible::Module::<T>::balance_of((id, from)));
+				// `from` holds `amount`, so only an overflow of the balance of
+				// `to` could fail the transfer, which then leaves both as
+				// they are.
+				let _ = fungible::Module::<T>::do_transfer(id, from.clone(), to.clone(), amount);
+			}
+		}
+	}
+
+	/// Adds the prices a swap held since its last update to its accumulators.
+	///
+	/// Runs before the first change to the reserves in each block so that
+	/// every block is weighted by the prices it was entered with.
+	fn update_price_oracle(swap_id: T::SwapId) -> dispatch::DispatchResult {
+		let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let now = system::Module::<T>::block_number();
+		if swap.price_updated >= now {
+			return Ok(());
+		}
+
+		let (token_price_cumulative, base_price_cumulative) = Self::cumulative_prices(swap_id, &swap, now)?;
+		swap.token_price_cumulative = token_price_cumulative;
+		swap.base_price_cumulative = base_price_cumulative;
+		swap.price_updated = now;
+		<Swaps<T>>::insert(swap_id, swap);
+
+		<PriceObservations<T>>::mutate(swap_id, |observations| {
+			if observations.len() >= MAX_PRICE_OBSERVATIONS {
+				observations.remove(0);
+			}
+			observations.push(PriceObservation {
+				block: now,
+				token_price_cumulative,
+				base_price_cumulative,
+			});
+		});
+
+		Ok(())
+	}
+
+	/// The price accumulators of `swap` brought forward to `now` at its
+	/// current spot prices. A swap without liquidity accumulates nothing.
+	fn cumulative_prices(swap_id: T::SwapId, swap: &SwapOf<T>, now: T::BlockNumber)
+		-> Result<(U256, U256), DispatchError>
+	{
+		let unchanged = (swap.token_price_cumulative, swap.base_price_cumulative);
+		let elapsed = U256::from(now.saturating_sub(swap.price_updated).saturated_into::<u128>());
+		if elapsed.is_zero() {
+			return Ok(unchanged);
+		}
+
+		// A StableSwap price too large to compute is left out of the average
+		// rather than blocking trades.
+		let (token_price, base_price) = match Self::spot_prices(swap_id, swap)? {
+			Some(prices) => prices,
+			None => return Ok(unchanged),
+		};
+		// The accumulators wrap, as only differences between them are
+		// meaningful.
+		Ok((
+			swap.token_price_cumulative.overflowing_add(token_price.overflowing_mul(elapsed).0).0,
+			swap.base_price_cumulative.overflowing_add(base_price.overflowing_mul(elapsed).0).0,
+		))
+	}
+
+	/// The spot prices of `swap` as (base per token, tokens per base) scaled
+	/// by `PRICE_PRECISION`, or `None` while it has no liquidity or a price
+	/// too large to compute.
+	fn spot_prices(swap_id: T::SwapId, swap: &SwapOf<T>) -> Result<Option<(U256, U256)>, DispatchError> {
+		Self::spot_prices_at(swap, Self::concentrated_pool(swap_id))
+	}
+
+	/// The spot prices of `swap` at its reserves, or at `pool` for
+	/// concentrated liquidity.
+	fn spot_prices_at(swap: &SwapOf<T>, pool: Option<ConcentratedPool>)
+		-> Result<Option<(U256, U256)>, DispatchError>
+	{
+		let base_reserve = Self::to_u256(Self::get_base_reserve(swap))?;
+		let token_reserve = Self::to_u256(Self::get_token_reserve(swap))?;
+		if base_reserve.is_zero() || token_reserve.is_zero() {
+			return Ok(None);
+		}
+
+		// Reserves fit in 128 bits, so scaling them by the precision cannot
+		// overflow.
+		let precision = U256::from(PRICE_PRECISION);
+		let prices = if Self::tick_spacing(swap).is_some() {
+			// A concentrated liquidity swap has no price while no position is
+			// in range.
+			pool.filter(|pool| pool.liquidity > 0)
+				.and_then(|pool| concentrated::spot_prices(pool.sqrt_price, precision))
+				.map(|(base_price, token_price)| (token_price, base_price))
+		} else if let Some(a) = Self::amplification(swap) {
+			stable_swap::spot_price(base_reserve, token_reserve, a, precision)
+				.and_then(|token_price| {
+					Some((token_price, stable_swap::spot_price(token_reserve, base_reserve, a, precision)?))
+				})
+		} else if let Some((base_weight, token_weight)) = Self::weights(swap, swap.base) {
+			// Each reserve counts in proportion to the inverse of its weight.
+			let (base_weight, token_weight) = (U256::from(base_weight), U256::from(token_weight));
+			Some((
+				base_reserve * precision * token_weight / (token_reserve * base_weight),
+				token_reserve * precision * base_weight / (base_reserve * token_weight),
+			))
+		} else {
+			Some((base_reserve * precision / token_reserve, token_reserve * precision / base_reserve))
+		};
+		Ok(prices)
+	}
+
+	/// Starts a new circuit breaker window for a swap at its current token
+	/// price once the last one is over.
+	fn update_reference_price(swap_id: T::SwapId, swap: &SwapOf<T>) -> dispatch::DispatchResult {
+		if T::CircuitBreakerThreshold::get().deconstruct() == 0 {
+			return Ok(());
+		}
+
+		let now = system::Module::<T>::block_number();
+		let window = T::CircuitBreakerWindow::get();
+		if Self::reference_price(swap_id).map_or(false, |(start, _)| now < start.saturating_add(window)) {
+			return Ok(());
+		}
+		match Self::spot_prices(swap_id, swap)? {
+			Some((token_price, _)) => <ReferencePrices<T>>::insert(swap_id, (now, token_price)),
+			None => <ReferencePrices<T>>::remove(swap_id),
+		}
+		Ok(())
+	}
+
+	/// The token price the current circuit breaker window of a swap started
+	/// at, which is its current price once the last window is over.
+	fn window_reference_price(swap_id: T::SwapId, swap: &SwapOf<T>) -> Result<Option<U256>, DispatchError> {
+		let now = system::Module::<T>::block_number();
+		let window = T::CircuitBreakerWindow::get();
+		match Self::reference_price(swap_id) {
+			Some((start, price)) if now < start.saturating_add(window) => Ok(Some(price)),
+			_ => Ok(Self::spot_prices(swap_id, swap)?.map(|(token_price, _)| token_price)),
+		}
+	}
+
+	/// Checks, before any funds move, that a trade fits the reserves of a
+	/// swap and leaves its token price within `CircuitBreakerThreshold` of
+	/// where the current circuit breaker window started, pausing the swap if
+	/// it does not.
+	fn ensure_within_circuit_breaker(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		asset_in: Asset<T::TokenId>,
+		amount_in: T::TokenBalance,
+		amount_out: T::TokenBalance,
+		exact_in: bool,
+	) -> dispatch::DispatchResult
+	{
+		let (_, after, trade) = Self::simulate_trade(swap_id, swap, asset_in, amount_in, amount_out, exact_in)?;
+		Self::ensure_price_within_circuit_breaker(swap_id, swap, &after, trade.map(|trade| trade.pool))
+	}
+
+	/// Checks that `after`, the state a change would leave `swap` in, keeps
+	/// its token price within `CircuitBreakerThreshold` of where the current
+	/// circuit breaker window started.
+	///
+	/// Otherwise the breaker trips: the swap is paused until `AdminOrigin`
+	/// unpauses it, and the change fails. The pause is kept as nothing is
+	/// rolled back when a call fails.
+	fn ensure_price_within_circuit_breaker(
+		swap_id: T::SwapId,
+		swap: &SwapOf<T>,
+		after: &SwapOf<T>,
+		pool: Option<ConcentratedPool>,
+	) -> dispatch::DispatchResult
+	{
+		let threshold = T::CircuitBreakerThreshold::get().deconstruct();
+		if threshold == 0 {
+			return Ok(());
+		}
+		let reference = match Self::window_reference_price(swap_id, swap)? {
+			Some(reference) if !reference.is_zero() => reference,
+			_ => return Ok(()),
+		};
+		let pool = pool.or_else(|| Self::concentrated_pool(swap_id));
+		let price = match Self::spot_prices_at(after, pool)? {
+			Some((token_price, _)) => token_price,
+			None => return Ok(()),
+		};
+
+		let moved = if price > reference { price - reference } else { reference - price };
+		let accuracy = U256::from(Permill::one().deconstruct());
+		if moved.full_mul(accuracy) > reference.full_mul(U256::from(threshold)) {
+			<PausedSwaps<T>>::insert(swap_id, true);
+			Self::deposit_event(RawEvent::CircuitBreakerTripped(swap_id));
+			return Err(Error::<T>::CircuitBreakerTripped.into());
+		}
+		Ok(())
+	}
+
+	/// Checks that trades may go through a swap.
+	fn ensure_trading_allowed(swap_id: T::SwapId) -> dispatch::DispatchResult {
+		ensure!(!Self::trading_paused(), Error::<T>::TradingPaused);
+		ensure!(!Self::swap_paused(swap_id), Error::<T>::SwapPaused);
+		Self::ensure_unlocked(swap_id)
+	}
+
+	/// Checks that liquidity may be added to a swap.
+	fn ensure_adding_liquidity_allowed(swap_id: T::SwapId) -> dispatch::DispatchResult {
+		ensure!(!Self::liquidity_paused(), Error::<T>::LiquidityPaused);
+		ensure!(!Self::swap_paused(swap_id), Error::<T>::SwapPaused);
+		Self::ensure_unlocked(swap_id)
+	}
+
+	/// Checks that a swap has no flash swap loan out, during which its
+	/// reserves are stale.
+	fn ensure_unlocked(swap_id: T::SwapId) -> dispatch::DispatchResult {
+		ensure!(!Self::swap_locked(swap_id), Error::<T>::SwapLocked);
+		Ok(())
+	}
+
+	/// The amplification `swap` prices trades with in the current block, or
+	/// `None` for a constant product swap.
+	fn amplification(swap: &SwapOf<T>) -> Option<u32> {
+		match swap.curve {
+			Curve::ConstantProduct | Curve::Weighted { .. } | Curve::Concentrated { .. } => None,
+			Curve::StableSwap { initial_a, future_a, ramp_start, ramp_end } => {
+				let now = system::Module::<T>::block_number();
+				if now >= ramp_end {
+					return Some(future_a);
+				}
+
+				let elapsed = now.saturating_sub(ramp_start).saturated_into::<u128>();
+				let duration = ramp_end.saturating_sub(ramp_start).saturated_into::<u128>();
+				let (initial_a, future_a) = (u128::from(initial_a), u128::from(future_a));
+				let a = if future_a >= initial_a {
+					initial_a + (future_a - initial_a) * elapsed / duration
+				} else {
+					initial_a - (initial_a - future_a) * elapsed / duration
+				};
+				Some(a.saturated_into())
+			},
+		}
+	}
+
+	/// The weights of the input and output side of `swap` when selling
+	/// `asset_in`, in parts per million, or `None` unless the swap is weighted.
+	fn weights(swap: &SwapOf<T>, asset_in: Asset<T::TokenId>) -> Option<(u32, u32)> {
+		match swap.curve {
+			Curve::Weighted { base_weight } => {
+				let base_weight = base_weight.deconstruct();
+				let token_weight = Permill::one().deconstruct() - base_weight;
+				if asset_in == swap.base {
+					Some((base_weight, token_weight))
+				} else {
+					Some((token_weight, base_weight))
+				}
+			},
+			_ => None,
+		}
+	}
+
+	/// The tick spacing of `swap`, or `None` unless it uses concentrated
+	/// liquidity.
+	fn tick_spacing(swap: &SwapOf<T>) -> Option<i32> {
+		match swap.curve {
+			Curve::Concentrated { tick_spacing } => Some(tick_spacing as i32),
+			_ => None,
+		}
+	}
+
+	/// The liquidity provider fee charged by `swap`.
+	fn swap_fee(swap: &SwapOf<T>) -> Permill {
+		// A fee of 100% would leave nothing of the input to price a trade
+		// with.
+		swap.fee.unwrap_or_else(|| T::LpFee::get().min(Permill::from_parts(999_999)))
+	}
+
+	fn convert(balance_of: BalanceOf<T>) -> Result<T::TokenBalance, Error<T>> {
+		T::BalanceConvert::to_token_balance(balance_of).ok_or(Error::<T>::Overflow)
+	}
+
+	fn unconvert(token_balance: T::TokenBalance) -> Result<BalanceOf<T>, Error<T>> {
+		T::BalanceConvert::to_balance(token_balance).ok_or(Error::<T>::Overflow)
+	}
+
+	fn get_token_reserve(swap: &SwapOf<T>) -> T::TokenBalance {
+		swap.token_reserve
+	}
+
+	fn get_base_reserve(swap: &SwapOf<T>) -> T::TokenBalance {
+		swap.base_reserve
+	}
+
+	/// What the account of `swap` holds of the base asset and tokens, less
+	/// unclaimed protocol fees. Anything above the reserves was sent to the
+	/// swap outside of its dispatchables.
+	fn held_balances(swap: &SwapOf<T>) -> Result<(T::TokenBalance, T::TokenBalance), Error<T>> {
+		let base = Self::asset_balance(swap.base, &swap.account)?.saturating_sub(swap.protocol_base);
+		let tokens = fungible::Module::<T>::balance_of((swap.token_id, &swap.account))
+			.saturating_sub(swap.protocol_tokens);
+		Ok((base, tokens))
+	}
+
+	/// Adds what was paid into a swap to its reserves and takes away what was
+	/// paid out of it.
+	fn update_reserves(
+		swap_id: T::SwapId,
+		base_in: T::TokenBalance,
+		tokens_in: T::TokenBalance,
+		base_out: T::TokenBalance,
+		tokens_out: T::TokenBalance,
+	) -> dispatch::DispatchResult
+	{
+		let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		swap.base_reserve = swap.base_reserve
+			.checked_add(&base_in)
+			.ok_or(Error::<T>::Overflow)?
+			.checked_sub(&base_out)
+			.ok_or(Error::<T>::InsufficientReserve)?;
+		swap.token_reserve = swap.token_reserve
+			.checked_add(&tokens_in)
+			.ok_or(Error::<T>::Overflow)?
+			.checked_sub(&tokens_out)
+			.ok_or(Error::<T>::InsufficientReserve)?;
+		<Swaps<T>>::insert(swap_id, swap);
+		Ok(())
+	}
+
+	/// Sets the reserves of a swap to its held balances.
+	fn sync_reserves(swap_id: T::SwapId) -> dispatch::DispatchResult {
+		let mut swap = Self::swaps(swap_id).ok_or(Error::<T>::NoSwapExists)?;
+		let (base_reserve, token_reserve) = Self::held_balances(&swap)?;
+		swap.base_reserve = base_reserve;
+		swap.token_reserve = token_reserve;
+		<Swaps<T>>::insert(swap_id, swap);
+
+		Self::deposit_event(RawEvent::ReservesSynced(swap_id, base_reserve, token_reserve));
+
+		Ok(())
+	}
+
+	fn asset_balance(asset: Asset<T::TokenId>, who: &T::AccountId) -> Result<T::TokenBalance, Error<T>> {
+		match asset {
+			Asset::Currency => Self::convert(T::Currency::free_balance(who)),
+			Asset::Token(id) => Ok(fungible::Module::<T>::balance_of((id, who))),
+		}
 	}
 }
@@ -1,10 +1,15 @@
 // Creating mock runtime here
 
-use crate::{Module, Trait};
+use crate::{Module, Trait, Asset, CheckedConvert, FlashSwapHandler};
+use codec::Decode;
+use std::cell::RefCell;
 use sp_core::H256;
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use frame_support::{
+	impl_outer_origin, parameter_types, dispatch::DispatchResult, weights::Weight,
+	traits::{Currency, ExistenceRequirement, Get},
+};
 use sp_runtime::{
-	traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
+	traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill, Permill,
 };
 
 impl_outer_origin! {
@@ -44,10 +49,6 @@ impl system::Trait for Test {
 	type OnKilledAccount = ();
 }
 
-parameter_types! {
-	pub const ExistentialDeposit: u64 = 1;
-}
-
 impl pallet_balances::Trait for Test {
 	type Balance = u64;
 	type Event = ();
@@ -56,18 +57,128 @@ impl pallet_balances::Trait for Test {
 	type AccountStore = system::Module<Test>;
 }
 
+parameter_types! {
+	pub const LpFee: Permill = Permill::from_parts(3_000);
+	pub const ProtocolFee: Permill = Permill::from_percent(20);
+	pub const ProtocolFeeDestination: u64 = 99;
+	pub const OrderTip: Permill = Permill::from_percent(1);
+}
+
+thread_local! {
+	static MINIMUM_LIQUIDITY: RefCell<u64> = RefCell::new(0);
+	static MINIMUM_DEPOSIT: RefCell<u64> = RefCell::new(0);
+	static CIRCUIT_BREAKER_THRESHOLD: RefCell<Permill> = RefCell::new(Permill::zero());
+	static CIRCUIT_BREAKER_WINDOW: RefCell<u64> = RefCell::new(0);
+	static EXISTENTIAL_DEPOSIT: RefCell<u64> = RefCell::new(1);
+}
+
+pub struct ExistentialDeposit;
+
+impl Get<u64> for ExistentialDeposit {
+	fn get() -> u64 {
+		EXISTENTIAL_DEPOSIT.with(|v| *v.borrow())
+	}
+}
+
+pub fn set_existential_deposit(amount: u64) {
+	EXISTENTIAL_DEPOSIT.with(|v| *v.borrow_mut() = amount);
+}
+
+/// Both bootstrap limits are off unless a test sets them, so that share
+/// amounts elsewhere stay easy to follow.
+pub struct MinimumLiquidity;
+
+impl Get<u64> for MinimumLiquidity {
+	fn get() -> u64 {
+		MINIMUM_LIQUIDITY.with(|v| *v.borrow())
+	}
+}
+
+pub struct MinimumDeposit;
+
+impl Get<u64> for MinimumDeposit {
+	fn get() -> u64 {
+		MINIMUM_DEPOSIT.with(|v| *v.borrow())
+	}
+}
+
+pub fn set_bootstrap_limits(minimum_liquidity: u64, minimum_deposit: u64) {
+	MINIMUM_LIQUIDITY.with(|v| *v.borrow_mut() = minimum_liquidity);
+	MINIMUM_DEPOSIT.with(|v| *v.borrow_mut() = minimum_deposit);
+}
+
+/// The circuit breaker is off unless a test sets it.
+pub struct CircuitBreakerThreshold;
+
+impl Get<Permill> for CircuitBreakerThreshold {
+	fn get() -> Permill {
+		CIRCUIT_BREAKER_THRESHOLD.with(|v| *v.borrow())
+	}
+}
+
+pub struct CircuitBreakerWindow;
+
+impl Get<u64> for CircuitBreakerWindow {
+	fn get() -> u64 {
+		CIRCUIT_BREAKER_WINDOW.with(|v| *v.borrow())
+	}
+}
+
+pub fn set_circuit_breaker(threshold: Permill, window: u64) {
+	CIRCUIT_BREAKER_THRESHOLD.with(|v| *v.borrow_mut() = threshold);
+	CIRCUIT_BREAKER_WINDOW.with(|v| *v.borrow_mut() = window);
+}
+
+/// Pays a flash swap back with the `(currency, token_id, tokens)` encoded in
+/// its data.
+pub struct FlashSwapRepayer;
+
+impl FlashSwapHandler<u64, u64, u64> for FlashSwapRepayer {
+	fn on_flash_swap(_: u64, borrower: &u64, swap_account: &u64, _: u64, _: u64, data: &[u8]) -> DispatchResult {
+		let (currency, token_id, tokens) = <(u64, u64, u64)>::decode(&mut &data[..])
+			.map_err(|_| "Invalid repayment")?;
+		if currency > 0 {
+			<Balances as Currency<u64>>::transfer(borrower, swap_account, currency, ExistenceRequirement::AllowDeath)?;
+		}
+		if tokens > 0 {
+			Fungible::do_transfer(token_id, *borrower, *swap_account, tokens)?;
+		}
+		Ok(())
+	}
+
+	fn weight(_: u64, _: u64, _: &[u8]) -> Weight {
+		100_000
+	}
+}
+
 impl Trait for Test {
 	type Event = ();
 	type SwapId = u64;
+	type PositionId = u64;
+	type OrderId = u64;
 	type Currency = pallet_balances::Module<Test>;
+	type BalanceConvert = CheckedConvert;
+	type FlashSwapHandler = FlashSwapRepayer;
+	type LpFee = LpFee;
+	type ProtocolFee = ProtocolFee;
+	type ProtocolFeeDestination = ProtocolFeeDestination;
+	type MinimumLiquidity = MinimumLiquidity;
+	type MinimumDeposit = MinimumDeposit;
+	type OrderTip = OrderTip;
+	type AdminOrigin = system::EnsureRoot<u64>;
+	type CircuitBreakerThreshold = CircuitBreakerThreshold;
+	type CircuitBreakerWindow = CircuitBreakerWindow;
+	type WeightInfo = ();
 }
 
 impl pallet_fungible::Trait for Test {
 	type Event = ();
 	type TokenBalance = u64;
 	type TokenId = u64;
+	type WeightInfo = ();
 }
 
+pub type System = system::Module<Test>;
 pub type Balances = pallet_balances::Module<Test>;
 pub type Fungible = pallet_fungible::Module<Test>;
 pub type Swaps = Module<Test>;
@@ -87,3 +198,23 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 	}.assimilate_storage(&mut t).unwrap();
 	t.into()
 }
+
+/// Like `new_test_ext`, with TokenId 0 and 1 created at genesis and held by
+/// account 1, and account 3 owning SwapId 0 for TokenId 0 against the
+/// currency and SwapId 1 for TokenId 1 priced in TokenId 0.
+pub fn new_test_ext_with_pools() -> sp_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(1, 10000), (2, 10000), (3, 10000)],
+	}.assimilate_storage(&mut t).unwrap();
+	pallet_fungible::GenesisConfig::<Test> {
+		balances: vec![(0, 1, 1000), (1, 1, 500)],
+	}.assimilate_storage(&mut t).unwrap();
+	crate::GenesisConfig::<Test> {
+		pools: vec![
+			(Asset::Currency, 400, Asset::Token(0), 100, 3),
+			(Asset::Token(1), 50, Asset::Token(0), 200, 3),
+		],
+	}.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
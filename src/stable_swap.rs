@@ -0,0 +1,103 @@
+//! The StableSwap invariant for a pair of assets, after Curve's `StableSwap`
+//! contract.
+//!
+//! `amplification` is Curve's `A`: the higher it is, the flatter the curve is
+//! around a balanced pool. Every function returns `None` if an intermediate
+//! value overflows or the iteration does not converge.
+
+use sp_core::U256;
+
+/// The number of assets in a swap.
+const N_COINS: u64 = 2;
+
+/// The Newton iterations run before giving up on convergence.
+const MAX_ITERATIONS: usize = 255;
+
+/// The invariant `D` of a pool holding `x` and `y`.
+pub fn invariant(x: U256, y: U256, amplification: u32) -> Option<U256> {
+	let sum = x.checked_add(y)?;
+	if sum.is_zero() {
+		return Some(U256::zero());
+	}
+
+	let n = U256::from(N_COINS);
+	let ann = U256::from(amplification).checked_mul(n)?;
+	let mut d = sum;
+	for _ in 0..MAX_ITERATIONS {
+		// D^(n+1) / (n^n * x * y)
+		let mut d_p = d;
+		for reserve in &[x, y] {
+			d_p = d_p.checked_mul(d)?.checked_div(reserve.checked_mul(n)?)?;
+		}
+
+		let previous = d;
+		let numerator = ann.checked_mul(sum)?
+			.checked_add(d_p.checked_mul(n)?)?
+			.checked_mul(d)?;
+		let denominator = ann.checked_sub(U256::one())?
+			.checked_mul(d)?
+			.checked_add(n.checked_add(U256::one())?.checked_mul(d_p)?)?;
+		d = numerator.checked_div(denominator)?;
+
+		if abs_diff(d, previous) <= U256::one() {
+			return Some(d);
+		}
+	}
+	None
+}
+
+/// The reserve of one asset that keeps the invariant at `d` when the pool
+/// holds `x` of the other.
+pub fn other_reserve(x: U256, d: U256, amplification: u32) -> Option<U256> {
+	let n = U256::from(N_COINS);
+	let ann = U256::from(amplification).checked_mul(n)?;
+	let c = d.checked_mul(d)?
+		.checked_div(x.checked_mul(n)?)?
+		.checked_mul(d)?
+		.checked_div(ann.checked_mul(n)?)?;
+	let b = x.checked_add(d.checked_div(ann)?)?;
+
+	let mut y = d;
+	for _ in 0..MAX_ITERATIONS {
+		let previous = y;
+		let numerator = y.checked_mul(y)?.checked_add(c)?;
+		let denominator = y.checked_mul(U256::from(2))?
+			.checked_add(b)?
+			.checked_sub(d)?;
+		y = numerator.checked_div(denominator)?;
+
+		if abs_diff(y, previous) <= U256::one() {
+			return Some(y);
+		}
+	}
+	None
+}
+
+/// The marginal price of `y` in `x` for a pool holding `x` and `y`, scaled by
+/// `precision`.
+pub fn spot_price(x: U256, y: U256, amplification: u32, precision: U256) -> Option<U256> {
+	let d = invariant(x, y, amplification)?;
+	let ann = U256::from(amplification)
+		.checked_mul(U256::from(N_COINS))?
+		.checked_mul(precision)?;
+	let d_x = d.checked_mul(precision)?.checked_div(x)?;
+	let d_y = d.checked_mul(precision)?.checked_div(y)?;
+
+	// The partial derivatives of the invariant, `Ann + D^3 / (4 x^2 y)` and
+	// `Ann + D^3 / (4 x y^2)`, scaled by `precision`.
+	let quarter = |a: U256, b: U256, c: U256| -> Option<U256> {
+		a.checked_mul(b)?
+			.checked_div(precision)?
+			.checked_mul(c)?
+			.checked_div(precision)?
+			.checked_div(U256::from(4))
+	};
+	let slope_x = ann.checked_add(quarter(d_x, d_x, d_y)?)?;
+	let slope_y = ann.checked_add(quarter(d_x, d_y, d_y)?)?;
+
+	slope_y.checked_mul(precision)?.checked_div(slope_x)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+	if a > b { a - b } else { b - a }
+}
@@ -1,5 +1,9 @@
-use crate::{Error, mock::*};
-use frame_support::{assert_ok, assert_noop};
+use crate::{Asset, BalanceConvert, CheckedConvert, Error, Side, PRICE_PRECISION, mock::*};
+use crate::concentrated::{MAX_TICK, MIN_TICK};
+use frame_support::{assert_ok, assert_noop, assert_err};
+use sp_core::U256;
+use sp_runtime::{DispatchError, Permill};
+use codec::Encode;
 
 #[test]
 fn creates_a_new_swap() {
@@ -11,7 +15,7 @@ fn creates_a_new_swap() {
 		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
 		assert_eq!(Swaps::swap_count(), 1);
 		assert_eq!(Fungible::token_count(), 2);
-		let swap_id = Swaps::token_to_swap(0);
+		let swap_id = Swaps::get_swap_by_token(0).unwrap();
 		assert_eq!(swap_id, 0);
 		let swap = Swaps::swaps(0).unwrap();
 		assert_eq!(swap.token_id, 0);
@@ -539,3 +543,1508 @@ fn it_allows_tokens_to_currency_output() {
 		assert_eq!(swap_bal, 420 - 135);
 	});
 }
+
+#[test]
+fn it_allows_tokens_to_tokens_input() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 420, 0, 42, 100));
+
+		// Give some of TokenId 0 to Account 2.
+		assert_ok!(Fungible::mint(0, 2, 42));
+
+		assert_noop!(
+			Swaps::tokens_to_tokens_input(
+				Origin::signed(2),
+				0,
+				20, // tokens sold
+				11, // min tokens bought too high
+				100,
+				2,
+				2,
+			),
+			Error::<Test>::NotEnoughTokens,
+		);
+
+		assert_noop!(
+			Swaps::tokens_to_swap_input(
+				Origin::signed(2),
+				0,
+				20,
+				1,
+				100,
+				2,
+				0, // same swap
+			),
+			Error::<Test>::IdenticalSwaps,
+		);
+
+		assert_ok!(
+			Swaps::tokens_to_tokens_input(
+				Origin::signed(2),
+				0,
+				20,
+				10,
+				100,
+				2,
+				2,
+			)
+		);
+
+		let swap = Swaps::swaps(0).unwrap();
+		let target = Swaps::swaps(1).unwrap();
+
+		// Account 2 sold TokenId 0...
+		assert_eq!(Fungible::balance_of((0, 2)), 42 - 20);
+		assert_eq!(Fungible::balance_of((0, &swap.account)), 42 + 20);
+		// ... and bought TokenId 2.
+		assert_eq!(Fungible::balance_of((2, 2)), 10);
+		assert_eq!(Fungible::balance_of((2, &target.account)), 42 - 10);
+
+		// Currency only moved between the swaps.
+		assert_eq!(Balances::free_balance(&2), 10000);
+		assert_eq!(Balances::free_balance(&swap.account), 420 - 135);
+		assert_eq!(Balances::free_balance(&target.account), 420 + 135);
+	});
+}
+
+#[test]
+fn it_allows_tokens_to_swap_output() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 420, 0, 42, 100));
+
+		assert_ok!(Fungible::mint(0, 2, 42));
+
+		assert_noop!(
+			Swaps::tokens_to_swap_output(
+				Origin::signed(2),
+				0,
+				10, // tokens bought
+				19, // max tokens sold too low
+				100,
+				2,
+				1,
+			),
+			Error::<Test>::TooExpensiveTokens,
+		);
+
+		assert_ok!(
+			Swaps::tokens_to_swap_output(
+				Origin::signed(2),
+				0,
+				10,
+				20,
+				100,
+				2,
+				1,
+			)
+		);
+
+		let swap = Swaps::swaps(0).unwrap();
+		let target = Swaps::swaps(1).unwrap();
+
+		assert_eq!(Fungible::balance_of((0, 2)), 42 - 20);
+		assert_eq!(Fungible::balance_of((0, &swap.account)), 42 + 20);
+		assert_eq!(Fungible::balance_of((2, 2)), 10);
+		assert_eq!(Fungible::balance_of((2, &target.account)), 42 - 10);
+
+		assert_eq!(Balances::free_balance(&swap.account), 420 - 132);
+		assert_eq!(Balances::free_balance(&target.account), 420 + 132);
+	});
+}
+
+#[test]
+fn it_swaps_exact_in_along_path() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 420, 0, 42, 100));
+
+		assert_ok!(Fungible::mint(0, 2, 42));
+
+		// Empty paths, repeated swaps and disconnected assets are rejected.
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![], Asset::Token(0), 20, 1, 100, 3),
+			Error::<Test>::InvalidPath,
+		);
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 0], Asset::Token(0), 20, 1, 100, 3),
+			Error::<Test>::InvalidPath,
+		);
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![1, 0], Asset::Token(0), 20, 1, 100, 3),
+			Error::<Test>::InvalidPath,
+		);
+		// A missing swap anywhere on the path fails the whole route.
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 7], Asset::Token(0), 20, 1, 100, 3),
+			Error::<Test>::NoSwapExists,
+		);
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 20, 11, 100, 3),
+			Error::<Test>::NotEnoughTokens,
+		);
+
+		assert_ok!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 20, 10, 100, 3)
+		);
+
+		let swap = Swaps::swaps(0).unwrap();
+		let target = Swaps::swaps(1).unwrap();
+
+		assert_eq!(Fungible::balance_of((0, 2)), 42 - 20);
+		assert_eq!(Fungible::balance_of((0, &swap.account)), 42 + 20);
+		assert_eq!(Fungible::balance_of((2, 3)), 10);
+		assert_eq!(Fungible::balance_of((2, &target.account)), 42 - 10);
+
+		assert_eq!(Balances::free_balance(&swap.account), 420 - 135);
+		assert_eq!(Balances::free_balance(&target.account), 420 + 135);
+	});
+}
+
+#[test]
+fn routes_that_fail_on_the_last_hop_have_no_effect() {
+	new_test_ext().execute_with(|| {
+		set_existential_deposit(10);
+
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// SwapId 0 trades TokenId 1 for TokenId 0 and SwapId 1 trades TokenId 1
+		// for currency.
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 100, 0, 100, 100));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 1));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 1000, 0, 100, 100));
+		assert_ok!(Fungible::mint(0, 2, 50));
+		let first = Swaps::swaps(0).unwrap().account;
+		let last = Swaps::swaps(1).unwrap().account;
+
+		// The last hop would pay account 8 less than the existential deposit,
+		// which fails the route before the buyer pays in.
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 2, 1, 100, 8),
+			Error::<Test>::BelowExistentialDeposit,
+		);
+		assert_eq!(Fungible::balance_of((0, 2)), 50);
+		assert_eq!(Fungible::balance_of((0, &first)), 100);
+		assert_eq!(Fungible::balance_of((1, &first)), 100);
+		assert_eq!(Fungible::balance_of((1, &last)), 100);
+		assert_eq!(Balances::free_balance(&last), 1000);
+		assert_eq!(Balances::free_balance(8), 0);
+		assert_eq!(Swaps::get_reserves(0), Some((100, 100)));
+		assert_eq!(Swaps::get_reserves(1), Some((1000, 100)));
+
+		// Enough to reach the existential deposit goes through.
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 4, 1, 100, 8));
+		assert_eq!(Fungible::balance_of((0, 2)), 46);
+		assert_eq!(Balances::free_balance(8), 29);
+	});
+}
+
+#[test]
+fn it_swaps_exact_out_along_path() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 420, 0, 42, 100));
+
+		assert_ok!(Fungible::mint(0, 2, 42));
+
+		assert_noop!(
+			Swaps::swap_exact_out_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 10, 19, 100, 3),
+			Error::<Test>::TooExpensiveTokens,
+		);
+
+		assert_ok!(
+			Swaps::swap_exact_out_along_path(Origin::signed(2), vec![0, 1], Asset::Token(0), 10, 20, 100, 3)
+		);
+
+		let swap = Swaps::swaps(0).unwrap();
+		let target = Swaps::swaps(1).unwrap();
+
+		assert_eq!(Fungible::balance_of((0, 2)), 42 - 20);
+		assert_eq!(Fungible::balance_of((2, 3)), 10);
+		assert_eq!(Balances::free_balance(&swap.account), 420 - 132);
+		assert_eq!(Balances::free_balance(&target.account), 420 + 132);
+	});
+}
+
+#[test]
+fn it_uses_the_swap_fee_override() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+
+		// Only root can change the fee.
+		assert_noop!(
+			Swaps::set_swap_fee(Origin::signed(1), 0, Some(Permill::from_percent(10))),
+			DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			Swaps::set_swap_fee(Origin::ROOT, 7, Some(Permill::from_percent(10))),
+			Error::<Test>::NoSwapExists,
+		);
+
+		// With the default 0.3% fee 300 currency buys 17 tokens.
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 300), Ok(17));
+
+		assert_ok!(Swaps::set_swap_fee(Origin::ROOT, 0, Some(Permill::from_percent(10))));
+		assert_eq!(Swaps::swaps(0).unwrap().fee, Some(Permill::from_percent(10)));
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 300), Ok(16));
+
+		assert_ok!(
+			Swaps::currency_to_tokens_input(
+				Origin::signed(2),
+				0,
+				300,
+				16,
+				100,
+				2
+			)
+		);
+		assert_eq!(Fungible::balance_of((0, 2)), 16);
+
+		// Removing the override restores the default.
+		assert_ok!(Swaps::set_swap_fee(Origin::ROOT, 0, None));
+		assert_eq!(Swaps::swaps(0).unwrap().fee, None);
+	});
+}
+
+#[test]
+fn cannot_set_a_fee_of_one_hundred_percent() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		assert_noop!(
+			Swaps::set_swap_fee(Origin::ROOT, 0, Some(Permill::one())),
+			Error::<Test>::InvalidFee,
+		);
+	});
+}
+
+#[test]
+fn it_accrues_and_claims_protocol_fees() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+		assert_ok!(Swaps::set_swap_fee(Origin::ROOT, 0, Some(Permill::from_percent(10))));
+
+		// Nothing to claim yet.
+		assert_eq!(Swaps::unclaimed_protocol_fees(0), Some((0, 0)));
+		assert_eq!(Swaps::unclaimed_protocol_fees(1), None);
+		assert_noop!(
+			Swaps::claim_protocol_fees(Origin::signed(3), 0),
+			Error::<Test>::NoProtocolFees,
+		);
+
+		// The 10% fee on 1000 currency is 100, of which 20% goes to the protocol.
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 1000, 1, 100, 2));
+		assert_eq!(Fungible::balance_of((0, 2)), 47);
+
+		let swap = Swaps::swaps(0).unwrap();
+		assert_eq!(swap.protocol_base, 20);
+		assert_eq!(swap.protocol_tokens, 0);
+		// The protocol's share stays in the account but is not a reserve.
+		assert_eq!(Balances::free_balance(&swap.account), 2000);
+		assert_eq!(Swaps::get_base_reserve(&swap), 1980);
+
+		// Selling tokens sets aside tokens.
+		assert_ok!(Fungible::mint(0, 2, 3));
+		assert_ok!(Swaps::tokens_to_currency_input(Origin::signed(2), 0, 50, 1, 100, 2));
+		let swap = Swaps::swaps(0).unwrap();
+		assert_eq!(swap.protocol_tokens, 1);
+		assert_eq!(Fungible::balance_of((0, &swap.account)), 103);
+		assert_eq!(Swaps::get_token_reserve(&swap), 102);
+		assert_eq!(Swaps::unclaimed_protocol_fees(0), Some((20, 1)));
+
+		// Anyone can send the fees to the destination.
+		assert_ok!(Swaps::claim_protocol_fees(Origin::signed(3), 0));
+		assert_eq!(Balances::free_balance(&99), 20);
+		assert_eq!(Fungible::balance_of((0, 99)), 1);
+		assert_eq!(Swaps::unclaimed_protocol_fees(0), Some((0, 0)));
+
+		let swap = Swaps::swaps(0).unwrap();
+		assert_eq!(swap.protocol_base, 0);
+		assert_eq!(swap.protocol_tokens, 0);
+	});
+}
+
+#[test]
+fn it_quotes_prices_by_swap_id() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Quotes fail for missing swaps and swaps without liquidity.
+		assert_eq!(Swaps::get_currency_to_token_input_price(7, 300), Err(Error::<Test>::NoSwapExists.into()));
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 300), Err(Error::<Test>::NoLiquidity.into()));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+
+		// Quotes match what the dispatchables execute in the tests above.
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 300), Ok(17));
+		assert_eq!(Swaps::get_currency_to_token_output_price(0, 17), Ok(287));
+		assert_eq!(Swaps::get_token_to_currency_input_price(0, 20), Ok(135));
+		assert_eq!(Swaps::get_token_to_currency_output_price(0, 135), Ok(20));
+
+		// The whole reserve can never be bought.
+		assert_eq!(Swaps::get_currency_to_token_output_price(0, 42), Err(Error::<Test>::InsufficientReserve.into()));
+		assert_eq!(Swaps::get_token_to_currency_output_price(0, 420), Err(Error::<Test>::InsufficientReserve.into()));
+		assert_noop!(
+			Swaps::currency_to_tokens_output(Origin::signed(2), 0, 42, 10000, 100, 2),
+			Error::<Test>::InsufficientReserve,
+		);
+	});
+}
+
+#[test]
+fn it_exposes_pool_state_and_route_quotes() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 1, 420, 0, 42, 100));
+
+		assert_eq!(Swaps::get_swap_by_token(0), Some(0));
+		assert_eq!(Swaps::get_swap_by_token(2), Some(1));
+		assert_eq!(Swaps::get_swap_by_token(1), None);
+
+		assert_eq!(Swaps::get_reserves(0), Some((420, 42)));
+		assert_eq!(Swaps::get_reserves(7), None);
+
+		// Half of the shares are worth half of the reserves.
+		assert_eq!(Swaps::lp_share_value(0, 210), Ok((210, 21)));
+		assert_eq!(Swaps::lp_share_value(7, 210), Err(Error::<Test>::NoSwapExists.into()));
+
+		// Route quotes match the router dispatchables.
+		assert_eq!(Swaps::quote_exact_in(vec![0, 1], Asset::Token(0), 20), Ok(10));
+		assert_eq!(Swaps::quote_exact_out(vec![0, 1], Asset::Token(0), 10), Ok(20));
+		assert_eq!(
+			Swaps::quote_exact_in(vec![1, 0], Asset::Token(0), 20),
+			Err(Error::<Test>::InvalidPath.into()),
+		);
+	});
+}
+
+#[test]
+fn it_lists_pools() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Create TokenId 2 and SwapId 1, creating TokenId 3 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 2));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+
+		let info = Swaps::pool_info(0).unwrap();
+		assert_eq!(info.token_id, 0);
+		assert_eq!(info.swap_token, 1);
+		assert_eq!(info.base_reserve, 420);
+		assert_eq!(info.token_reserve, 42);
+		assert_eq!(info.total_shares, 420);
+		assert_eq!(info.fee, Permill::from_parts(3_000));
+		assert_eq!(Swaps::pool_info(2), None);
+
+		let pools = Swaps::pools();
+		assert_eq!(pools.len(), 2);
+		assert_eq!(pools[0], (0, info));
+		assert_eq!(pools[1].0, 1);
+		assert_eq!(pools[1].1.token_reserve, 0);
+	});
+}
+
+#[test]
+fn tokens_to_currency_output_pays_the_recipient() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+
+		assert_ok!(Fungible::mint(0, 2, 42));
+
+		// Account 2 sells its tokens and Account 3 receives the currency.
+		assert_ok!(Swaps::tokens_to_currency_output(Origin::signed(2), 0, 135, 1000, 100, 3));
+
+		assert_eq!(Fungible::balance_of((0, 2)), 42 - 20);
+		assert_eq!(Fungible::balance_of((0, 3)), 0);
+		assert_eq!(Balances::free_balance(&2), 10000);
+		assert_eq!(Balances::free_balance(&3), 10000 + 135);
+	});
+}
+
+#[test]
+fn pricing_does_not_overflow_with_large_reserves() {
+	new_test_ext().execute_with(|| {
+		let fee = Permill::from_parts(3_000);
+		let big = 1_000_000_000_000_000_000u64;
+
+		// The intermediate products overflow u64 but the result fits.
+		assert_eq!(Swaps::get_input_price(big, big, big, fee), Ok(499_248_873_309_964_947));
+
+		// A result that does not fit the balance type is an error, not a panic.
+		assert_eq!(
+			Swaps::get_output_price(999, u64::max_value(), 1000, fee),
+			Err(Error::<Test>::Overflow.into()),
+		);
+
+		// Buying the whole reserve is rejected instead of underflowing.
+		assert_eq!(
+			Swaps::get_output_price(1000, big, 1000, fee),
+			Err(Error::<Test>::InsufficientReserve.into()),
+		);
+	});
+}
+
+#[test]
+fn cannot_remove_liquidity_with_shares_not_owned() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 42));
+
+		// Create SwapId 0 for TokenId 0, creating TokenId 1 as shares.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+
+		// Account 2 holds no shares.
+		assert_noop!(
+			Swaps::remove_liquidity(Origin::signed(2), 0, 210, 1, 1, 100),
+			pallet_fungible::Error::<Test>::InsufficientFunds,
+		);
+	});
+}
+
+#[test]
+fn balance_conversion_fails_instead_of_saturating() {
+	let large = u128::from(u64::max_value()) + 1;
+
+	assert_eq!(<CheckedConvert as BalanceConvert<u128, u64>>::to_token_balance(large), None);
+	assert_eq!(<CheckedConvert as BalanceConvert<u64, u128>>::to_balance(large), None);
+	assert_eq!(<CheckedConvert as BalanceConvert<u128, u128>>::to_token_balance(large), Some(large));
+	assert_eq!(<CheckedConvert as BalanceConvert<u128, u64>>::to_token_balance(42), Some(42));
+}
+
+#[test]
+fn consults_time_weighted_average_prices() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// Create SwapId 0 for TokenId 0 priced at 10 currency per token.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+
+		// Trades only move the average from the next block onwards.
+		System::set_block_number(11);
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 1000, 1, 100, 2));
+		assert_eq!(
+			Swaps::consult(0, 10),
+			Ok((10 * PRICE_PRECISION, PRICE_PRECISION / 10)),
+		);
+
+		// There is no observation from before the swap was created.
+		assert_noop!(Swaps::consult(0, 20), Error::<Test>::InsufficientHistory);
+
+		// Blocks without trades are weighted by the latest prices.
+		System::set_block_number(21);
+		let (currency, tokens) = Swaps::get_reserves(0).unwrap();
+		let (currency, tokens) = (u128::from(currency), u128::from(tokens));
+		assert_eq!(
+			Swaps::consult(0, 10),
+			Ok((currency * PRICE_PRECISION / tokens, tokens * PRICE_PRECISION / currency)),
+		);
+		assert_eq!(
+			Swaps::consult(0, 20),
+			Ok((
+				(10 * PRICE_PRECISION + currency * PRICE_PRECISION / tokens) / 2,
+				(PRICE_PRECISION / 10 + tokens * PRICE_PRECISION / currency) / 2,
+			)),
+		);
+	});
+}
+
+#[test]
+fn flash_swaps_must_be_paid_back_with_a_fee() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// Create SwapId 0 for TokenId 0 with 1000 currency and 100 tokens.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+		let account = Swaps::swaps(0).unwrap().account;
+		assert_ok!(Fungible::mint(0, 2, 50));
+
+		// Cannot borrow the whole reserve.
+		assert_noop!(
+			Swaps::flash_swap(Origin::signed(2), 0, 0, 100, (0u64, 0u64, 0u64).encode()),
+			Error::<Test>::InsufficientReserve,
+		);
+
+		// Paying back only what was borrowed fails.
+		assert_eq!(
+			Swaps::flash_swap(Origin::signed(2), 0, 0, 10, (0u64, 0u64, 10u64).encode()),
+			Err(Error::<Test>::FlashSwapNotRepaid.into()),
+		);
+		assert_eq!(Fungible::balance_of((0, &account)), 100);
+		assert_eq!(Fungible::balance_of((0, 2)), 50);
+
+		// Not paying back at all fails and the loan is taken back.
+		assert_eq!(
+			Swaps::flash_swap(Origin::signed(2), 0, 0, 10, (0u64, 0u64, 0u64).encode()),
+			Err(Error::<Test>::FlashSwapNotRepaid.into()),
+		);
+		assert_eq!(Fungible::balance_of((0, &account)), 100);
+		assert_eq!(Fungible::balance_of((0, 2)), 50);
+
+		// Paying back with the 0.3% fee on the tokens paid in succeeds.
+		assert_ok!(Swaps::flash_swap(Origin::signed(2), 0, 0, 10, (0u64, 0u64, 11u64).encode()));
+		assert_eq!(Fungible::balance_of((0, &account)), 101);
+		assert_eq!(Fungible::balance_of((0, 2)), 49);
+
+		// The loan can be paid back in the other asset.
+		assert_ok!(Swaps::flash_swap(Origin::signed(2), 0, 0, 10, (111u64, 0u64, 0u64).encode()));
+		assert_eq!(Balances::free_balance(&account), 1111);
+		assert_eq!(Fungible::balance_of((0, &account)), 91);
+		assert_eq!(Fungible::balance_of((0, 2)), 59);
+	});
+}
+
+#[test]
+fn failed_flash_swaps_are_taken_back_from_borrowers_with_nothing_else() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// Create SwapId 0 for TokenId 0 with 1000 currency and 100 tokens.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+		let account = Swaps::swaps(0).unwrap().account;
+
+		// Account 7 holds nothing, borrows both assets and repays nothing.
+		assert_eq!(Balances::free_balance(7), 0);
+		assert_eq!(
+			Swaps::flash_swap(Origin::signed(7), 0, 100, 10, (0u64, 0u64, 0u64).encode()),
+			Err(Error::<Test>::FlashSwapNotRepaid.into()),
+		);
+		assert_eq!(Balances::free_balance(7), 0);
+		assert_eq!(Fungible::balance_of((0, 7)), 0);
+		assert_eq!(Balances::free_balance(&account), 1000);
+		assert_eq!(Fungible::balance_of((0, &account)), 100);
+		let swap = Swaps::swaps(0).unwrap();
+		assert_eq!((swap.base_reserve, swap.token_reserve), (1000, 100));
+		assert!(!Swaps::swap_locked(0));
+	});
+}
+
+#[test]
+fn locked_swaps_cannot_be_traded_with() {
+	new_test_ext().execute_with(|| {
+		use frame_support::storage::StorageMap;
+
+		// Create TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// Create SwapId 0 for TokenId 0 with 1000 currency and 100 tokens.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+
+		// While a flash swap is out nothing else may touch the swap.
+		<crate::LockedSwaps<Test>>::insert(0, true);
+		assert_noop!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2),
+			Error::<Test>::SwapLocked,
+		);
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(2), 0, 100, 0, 10, 10),
+			Error::<Test>::SwapLocked,
+		);
+		assert_noop!(Swaps::remove_liquidity(Origin::signed(1), 0, 10, 1, 1, 100), Error::<Test>::SwapLocked);
+		assert_noop!(Swaps::flash_swap(Origin::signed(2), 0, 0, 10, (0u64, 0u64, 11u64).encode()), Error::<Test>::SwapLocked);
+		assert_noop!(Swaps::sync(Origin::signed(2), 0), Error::<Test>::SwapLocked);
+		assert_noop!(Swaps::skim(Origin::signed(2), 0, 2), Error::<Test>::SwapLocked);
+
+		<crate::LockedSwaps<Test>>::remove(0);
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2));
+	});
+}
+
+#[test]
+fn trades_pairs_of_tokens() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// A pair needs two different assets.
+		assert_noop!(
+			Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(0)),
+			Error::<Test>::IdenticalAssets,
+		);
+
+		// Create SwapId 0 for TokenId 1 priced in TokenId 0, creating TokenId 2
+		// as shares.
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(1), Asset::Token(0)));
+		assert_noop!(
+			Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)),
+			Error::<Test>::SwapAlreadyExists,
+		);
+		assert_eq!(Swaps::get_swap_by_pair(Asset::Token(0), Asset::Token(1)), Some(0));
+		assert_eq!(Swaps::get_swap_by_pair(Asset::Token(1), Asset::Token(0)), Some(0));
+		assert_eq!(Swaps::get_swap_by_token(1), None);
+
+		let info = Swaps::pool_info(0).unwrap();
+		assert_eq!(info.base, Asset::Token(0));
+		assert_eq!(info.token_id, 1);
+		assert_eq!(info.swap_token, 2);
+
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_eq!(Swaps::get_reserves(0), Some((420, 42)));
+		assert_eq!(Fungible::balance_of((2, 1)), 420);
+
+		// Tokens trade directly against each other.
+		assert_ok!(Fungible::mint(1, 2, 20));
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 20, 1, 100, 2));
+		assert_eq!(Fungible::balance_of((1, 2)), 0);
+		assert_eq!(Fungible::balance_of((0, 2)), 135);
+		assert_eq!(Swaps::get_reserves(0), Some((285, 62)));
+
+		// The currency dispatchables only work on currency swaps.
+		assert_noop!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2),
+			Error::<Test>::NotCurrencySwap,
+		);
+
+		// Liquidity is returned in both tokens.
+		assert_ok!(Swaps::remove_liquidity(Origin::signed(1), 0, 210, 1, 1, 100));
+		assert_eq!(Swaps::get_reserves(0), Some((143, 31)));
+	});
+}
+
+#[test]
+fn stable_swaps_trade_pegged_assets_with_low_slippage() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10000));
+
+		// Create SwapId 0 for the pair and switch it to StableSwap.
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_noop!(Swaps::set_swap_curve(Origin::signed(1), 0, Some(100)), DispatchError::BadOrigin);
+		assert_noop!(
+			Swaps::set_swap_curve(Origin::ROOT, 0, Some(0)),
+			Error::<Test>::InvalidAmplification,
+		);
+		assert_ok!(Swaps::set_swap_curve(Origin::ROOT, 0, Some(100)));
+		assert_eq!(Swaps::pool_info(0).unwrap().amplification, Some(100));
+
+		// The curve cannot be changed once the swap has liquidity.
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 1000, 100));
+		assert_noop!(
+			Swaps::set_swap_curve(Origin::ROOT, 0, None),
+			Error::<Test>::SwapHasLiquidity,
+		);
+
+		// Selling 100 tokens would only return 90 with constant product.
+		assert_eq!(Swaps::quote_exact_in(vec![0], Asset::Token(1), 100), Ok(98));
+		assert_eq!(Swaps::quote_exact_out(vec![0], Asset::Token(1), 99), Ok(101));
+
+		assert_ok!(Fungible::mint(1, 2, 100));
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 100, 98, 100, 2));
+		assert_eq!(Fungible::balance_of((0, 2)), 98);
+		assert_eq!(Swaps::get_reserves(0), Some((902, 1100)));
+	});
+}
+
+#[test]
+fn ramps_the_stable_swap_amplification() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+
+		// Create SwapId 0 with constant product and SwapId 1 with StableSwap.
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_ok!(Swaps::set_swap_curve(Origin::ROOT, 1, Some(100)));
+
+		assert_noop!(
+			Swaps::ramp_amplification(Origin::ROOT, 0, 1000, 11),
+			Error::<Test>::NotStableSwap,
+		);
+		// The ramp must end in the future and change A at most tenfold.
+		assert_noop!(
+			Swaps::ramp_amplification(Origin::ROOT, 1, 1000, 1),
+			Error::<Test>::InvalidAmplification,
+		);
+		assert_noop!(
+			Swaps::ramp_amplification(Origin::ROOT, 1, 1001, 11),
+			Error::<Test>::InvalidAmplification,
+		);
+		assert_noop!(
+			Swaps::ramp_amplification(Origin::ROOT, 1, 9, 11),
+			Error::<Test>::InvalidAmplification,
+		);
+
+		// A moves linearly over the ramp and stays at its target afterwards.
+		assert_ok!(Swaps::ramp_amplification(Origin::ROOT, 1, 1000, 11));
+		System::set_block_number(6);
+		assert_eq!(Swaps::pool_info(1).unwrap().amplification, Some(550));
+
+		// A new ramp starts from the current value.
+		assert_ok!(Swaps::ramp_amplification(Origin::ROOT, 1, 100, 16));
+		System::set_block_number(11);
+		assert_eq!(Swaps::pool_info(1).unwrap().amplification, Some(325));
+		System::set_block_number(20);
+		assert_eq!(Swaps::pool_info(1).unwrap().amplification, Some(100));
+	});
+}
+
+#[test]
+fn weighted_swaps_price_by_the_weighted_product() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10000));
+
+		// Create SwapId 0 holding 80% of its value in TokenId 0.
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_noop!(
+			Swaps::set_swap_weights(Origin::ROOT, 0, Permill::from_percent(99)),
+			Error::<Test>::InvalidWeight,
+		);
+		assert_ok!(Swaps::set_swap_weights(Origin::ROOT, 0, Permill::from_percent(80)));
+		assert_eq!(Swaps::pool_info(0).unwrap().base_weight, Some(Permill::from_percent(80)));
+
+		// 4000 of TokenId 0 against 1000 of TokenId 1 prices them one to one.
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 4000, 0, 1000, 100));
+		assert_noop!(
+			Swaps::set_swap_curve(Origin::ROOT, 0, Some(100)),
+			Error::<Test>::SwapHasLiquidity,
+		);
+
+		assert_eq!(Swaps::quote_exact_in(vec![0], Asset::Token(1), 100), Ok(93));
+		assert_eq!(Swaps::quote_exact_out(vec![0], Asset::Token(1), 300), Ok(368));
+		// Trades are limited to a share of the reserves.
+		assert_eq!(
+			Swaps::quote_exact_in(vec![0], Asset::Token(1), 501),
+			Err(Error::<Test>::TradeTooLarge.into()),
+		);
+
+		assert_ok!(Fungible::mint(1, 2, 100));
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 100, 93, 100, 2));
+		assert_eq!(Fungible::balance_of((0, 2)), 93);
+		assert_eq!(Swaps::get_reserves(0), Some((3907, 1100)));
+	});
+}
+
+#[test]
+fn concentrated_liquidity_trades_across_position_ranges() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and 1.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100000));
+
+		// Create SwapId 0 for the pair at a price of one, which is tick 0.
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		let price_of_one = U256::one() << 96;
+		assert_noop!(
+			Swaps::set_swap_concentrated(Origin::ROOT, 0, 0, price_of_one),
+			Error::<Test>::InvalidTickSpacing,
+		);
+		assert_noop!(
+			Swaps::set_swap_concentrated(Origin::ROOT, 0, 10, U256::zero()),
+			Error::<Test>::InvalidPrice,
+		);
+		assert_ok!(Swaps::set_swap_concentrated(Origin::ROOT, 0, 10, price_of_one));
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().tick, 0);
+
+		// Liquidity is held in positions instead of shares.
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 1000, 100),
+			Error::<Test>::ConcentratedSwap,
+		);
+		assert_noop!(
+			Swaps::set_swap_curve(Origin::ROOT, 0, Some(100)),
+			Error::<Test>::ConcentratedSwap,
+		);
+		// Its reserves are what positions are owed, so they are neither
+		// synced nor skimmed.
+		assert_noop!(Swaps::sync(Origin::signed(2), 0), Error::<Test>::ConcentratedSwap);
+		assert_noop!(Swaps::skim(Origin::signed(2), 0, 2), Error::<Test>::ConcentratedSwap);
+
+		// Position 0 is around the price and holds both assets, while
+		// position 1 is above it and only holds the base asset.
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 0, -105, 100, 1_000_000, 5000, 5000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 0, -100, 100, 1_000_000, 4987, 5000, 100),
+			Error::<Test>::TooMuchBase,
+		);
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 0, -100, 100, 1_000_000, 4988, 4988, 100));
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 0, 200, 300, 1_000_000, 4938, 0, 100));
+		assert_eq!(Swaps::get_reserves(0), Some((9926, 4988)));
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().liquidity, 1_000_000);
+
+		assert_eq!(Swaps::quote_exact_in(vec![0], Asset::Token(0), 1000), Ok(996));
+		assert_eq!(Swaps::quote_exact_out(vec![0], Asset::Token(1), 1000), Ok(1006));
+
+		// Selling 6000 tokens moves the price out of position 0 and across the
+		// gap into position 1.
+		assert_ok!(Fungible::mint(1, 2, 6000));
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 6000, 5934, 100, 2));
+		assert_eq!(Fungible::balance_of((0, 2)), 5934);
+		let pool = Swaps::pool_info(0).unwrap().concentrated.unwrap();
+		assert_eq!(pool.tick, 219);
+		assert_eq!(pool.liquidity, 1_000_000);
+		assert_eq!(Swaps::swaps(0).unwrap().protocol_tokens, 3);
+
+		// Past position 1 there is no liquidity left to trade against.
+		assert_eq!(
+			Swaps::quote_exact_out(vec![0], Asset::Token(1), 4000),
+			Err(Error::<Test>::TooManySwapSteps.into()),
+		);
+
+		// Each position earned the fees paid while it was in range.
+		assert_noop!(Swaps::collect_position_fees(Origin::signed(2), 0), Error::<Test>::NotPositionOwner);
+		assert_ok!(Swaps::collect_position_fees(Origin::signed(1), 0));
+		assert_eq!(Fungible::balance_of((1, 1)), 95024);
+		assert_noop!(Swaps::collect_position_fees(Origin::signed(1), 0), Error::<Test>::NoFeesOwed);
+
+		// Burning position 1 returns what is left of it along with its fees.
+		assert_ok!(Swaps::burn_position(Origin::signed(1), 1, 1_000_000, 3989, 967, 100));
+		assert_eq!(Fungible::balance_of((0, 1)), 94063);
+		assert_eq!(Fungible::balance_of((1, 1)), 95993);
+		assert_eq!(Swaps::positions(1), None);
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().liquidity, 0);
+	});
+}
+
+#[test]
+fn concentrated_liquidity_runs_out_at_the_edge_of_positions() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0 and 1 and SwapId 0 at a price of one, where a single
+		// word of the tick bitmap covers every tick.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10_000_000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 10_000_000));
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_ok!(Swaps::set_swap_concentrated(Origin::ROOT, 0, 16_384, U256::one() << 96));
+
+		// Position 0 is around the price, while position 1 is below it and only
+		// holds tokens.
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 0, -16_384, 16_384, 1_000_000, 600_000, 600_000, 100));
+		let base_deposit = 10_000_000 - Fungible::balance_of((0, 1));
+		let token_deposit = 10_000_000 - Fungible::balance_of((1, 1));
+		assert_eq!(Swaps::get_reserves(0), Some((base_deposit, token_deposit)));
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 0, -32_768, -16_384, 1_000_000, 0, 1_000_000, 100));
+		let out_of_range_deposit = 10_000_000 - token_deposit - Fungible::balance_of((1, 1));
+		assert!(out_of_range_deposit > 0);
+		assert_eq!(Fungible::balance_of((0, 1)), 10_000_000 - base_deposit);
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().liquidity, 1_000_000);
+
+		// A position cannot be burned for more liquidity than it holds.
+		assert_noop!(
+			Swaps::burn_position(Origin::signed(1), 0, 1_000_001, 0, 0, 100),
+			Error::<Test>::InsufficientLiquidity,
+		);
+		assert_noop!(Swaps::burn_position(Origin::signed(1), 0, 0, 0, 0, 100), Error::<Test>::BurnZeroShares);
+
+		// Buying more than the positions above the price hold runs out of
+		// liquidity before the trade is done.
+		assert_ok!(Fungible::mint(1, 2, 2_000_000));
+		assert_eq!(
+			Swaps::quote_exact_in(vec![0], Asset::Token(1), 2_000_000),
+			Err(Error::<Test>::InsufficientLiquidity.into()),
+		);
+		assert_noop!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 2_000_000, 1, 100, 2),
+			Error::<Test>::InsufficientLiquidity,
+		);
+
+		// Fees are collected as often as they are earned, and never by a
+		// position out of range.
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 100_000, 1, 100, 2));
+		let tokens_before = Fungible::balance_of((1, 1));
+		assert_ok!(Swaps::collect_position_fees(Origin::signed(1), 0));
+		let tokens_after_first = Fungible::balance_of((1, 1));
+		assert!(tokens_after_first > tokens_before);
+		assert_noop!(Swaps::collect_position_fees(Origin::signed(1), 0), Error::<Test>::NoFeesOwed);
+		assert_ok!(Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Token(1), 100_000, 1, 100, 2));
+		assert_ok!(Swaps::collect_position_fees(Origin::signed(1), 0));
+		assert!(Fungible::balance_of((1, 1)) > tokens_after_first);
+		assert_noop!(Swaps::collect_position_fees(Origin::signed(1), 1), Error::<Test>::NoFeesOwed);
+
+		// Burning position 1 returns its tokens, less rounding, and leaves the
+		// liquidity in range alone.
+		let base_before = Fungible::balance_of((0, 1));
+		let tokens_before = Fungible::balance_of((1, 1));
+		assert_ok!(Swaps::burn_position(Origin::signed(1), 1, 1_000_000, 0, out_of_range_deposit - 1, 100));
+		assert_eq!(Fungible::balance_of((0, 1)), base_before);
+		assert!(Fungible::balance_of((1, 1)) - tokens_before <= out_of_range_deposit);
+		assert_eq!(Swaps::positions(1), None);
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().liquidity, 1_000_000);
+	});
+}
+
+#[test]
+fn concentrated_positions_stay_within_the_range_of_ticks() {
+	new_test_ext().execute_with(|| {
+		// Create TokenId 0, 1 and 2, SwapId 0 with a tick spacing of one and
+		// SwapId 1 with a tick spacing of ten.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100000));
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100000));
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(1)));
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(2)));
+		assert_ok!(Swaps::set_swap_concentrated(Origin::ROOT, 0, 1, U256::one() << 96));
+		assert_ok!(Swaps::set_swap_concentrated(Origin::ROOT, 1, 10, U256::one() << 96));
+
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 0, MIN_TICK - 1, MAX_TICK, 1000, 2000, 2000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 0, MIN_TICK, MAX_TICK + 1, 1000, 2000, 2000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 0, MAX_TICK, MIN_TICK, 1000, 2000, 2000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+
+		// Liquidity over every tick holds as much of each asset as it would at
+		// any price.
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 0, MIN_TICK, MAX_TICK, 1000, 1000, 1000, 100));
+		assert_eq!(Swaps::get_reserves(0), Some((1000, 1000)));
+		assert_eq!(Swaps::pool_info(0).unwrap().concentrated.unwrap().liquidity, 1000);
+
+		// The bounds themselves are only usable where they are multiples of
+		// the tick spacing.
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 1, MIN_TICK, 10, 1000, 2000, 2000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+		assert_noop!(
+			Swaps::mint_position(Origin::signed(1), 1, -10, MAX_TICK, 1000, 2000, 2000, 100),
+			Error::<Test>::InvalidTickRange,
+		);
+		assert_ok!(Swaps::mint_position(Origin::signed(1), 1, MIN_TICK + 2, MAX_TICK - 2, 1000, 1000, 1000, 100));
+	});
+}
+
+#[test]
+fn seeds_pools_at_genesis() {
+	new_test_ext_with_pools().execute_with(|| {
+		// The swaps' shares are TokenId 2 and 3.
+		assert_eq!(Fungible::token_count(), 4);
+		assert_eq!(Swaps::swap_count(), 2);
+		assert_eq!(Fungible::total_supply(0), 1300);
+
+		assert_eq!(Swaps::get_reserves(0), Some((400, 100)));
+		assert_eq!(Fungible::balance_of((2, 3)), 400);
+		assert_eq!(Swaps::get_swap_by_pair(Asset::Token(1), Asset::Token(0)), Some(1));
+		assert_eq!(Swaps::get_reserves(1), Some((200, 50)));
+		assert_eq!(Fungible::balance_of((3, 3)), 200);
+
+		// The pools trade and their owner can withdraw from them.
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(1), 0, 100, 1, 100, 1));
+		assert_eq!(Fungible::balance_of((0, 1)), 1019);
+		assert_ok!(Swaps::remove_liquidity(Origin::signed(3), 1, 200, 200, 50, 100));
+		assert_eq!(Swaps::get_reserves(1), Some((0, 0)));
+	});
+}
+
+#[test]
+fn locks_minimum_liquidity_on_the_first_deposit() {
+	new_test_ext().execute_with(|| {
+		set_bootstrap_limits(10, 40);
+
+		// Create TokenId 0 and SwapId 0, creating TokenId 1 as shares.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+
+		// Both sides of the first deposit must meet the floor.
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(1), 0, 39, 0, 42, 100),
+			Error::<Test>::DepositTooSmall,
+		);
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 39, 100),
+			Error::<Test>::DepositTooSmall,
+		);
+
+		// The first 10 shares are locked away for good.
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 420, 0, 42, 100));
+		assert_eq!(Fungible::total_supply(1), 420);
+		assert_eq!(Fungible::balance_of((1, 1)), 410);
+		assert_eq!(Fungible::balance_of((1, Swaps::locked_liquidity_account())), 10);
+
+		// Later deposits mint shares as usual and are not held to the floor.
+		assert_ok!(Fungible::mint(0, 2, 10));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(2), 0, 20, 1, 2, 100));
+		assert_eq!(Fungible::balance_of((1, 2)), 20);
+
+		// Withdrawing every unlocked share leaves the locked ones behind.
+		assert_ok!(Swaps::remove_liquidity(Origin::signed(1), 0, 410, 1, 1, 100));
+		assert_ok!(Swaps::remove_liquidity(Origin::signed(2), 0, 20, 1, 1, 100));
+		assert_eq!(Swaps::get_reserves(0), Some((10, 1)));
+	});
+}
+
+#[test]
+fn ignores_donations_until_synced() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 100));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 1000, 0, 100, 100));
+		let account = Swaps::swaps(0).unwrap().account;
+
+		// Sending funds straight to the swap account does not move its price.
+		assert_ok!(Fungible::mint(0, 2, 100));
+		assert_ok!(Fungible::do_transfer(0, 2, account, 50));
+		assert_ok!(Balances::transfer(Origin::signed(2), account, 500));
+		assert_eq!(Swaps::get_reserves(0), Some((1000, 100)));
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 100), Ok(9));
+
+		// Anyone can skim the excess off to any account.
+		assert_ok!(Swaps::skim(Origin::signed(3), 0, 4));
+		assert_eq!(Fungible::balance_of((0, 4)), 50);
+		assert_eq!(Balances::free_balance(&4), 10500);
+		assert_eq!(Balances::free_balance(&account), 1000);
+		assert_eq!(Swaps::get_reserves(0), Some((1000, 100)));
+
+		// Syncing counts a donation towards the reserves.
+		assert_ok!(Fungible::do_transfer(0, 2, account, 50));
+		assert_ok!(Swaps::sync(Origin::signed(3), 0));
+		assert_eq!(Swaps::get_reserves(0), Some((1000, 150)));
+		assert_eq!(Swaps::get_currency_to_token_input_price(0, 100), Ok(13));
+
+		// Trades keep the reserves in step with the balances.
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2));
+		let swap = Swaps::swaps(0).unwrap();
+		assert_eq!(Swaps::get_reserves(0), Some((1100, 137)));
+		assert_eq!(Balances::free_balance(&account), 1100);
+		assert_eq!(Fungible::balance_of((0, &account)), 137);
+		assert_eq!(swap.protocol_base, 0);
+	});
+}
+
+#[test]
+fn adds_liquidity_from_a_single_asset() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+
+		assert_noop!(
+			Swaps::add_liquidity_single_sided(Origin::signed(2), 0, Asset::Token(1), 1000, 1, 100),
+			Error::<Test>::AssetNotInSwap,
+		);
+		assert_noop!(
+			Swaps::add_liquidity_single_sided(Origin::signed(2), 0, Asset::Currency, 1000, 448, 100),
+			Error::<Test>::TooLowLiquidity,
+		);
+
+		// 451 currency buys 183 tokens, which match the other 549 exactly.
+		assert_ok!(Swaps::add_liquidity_single_sided(Origin::signed(2), 0, Asset::Currency, 1000, 447, 100));
+		assert_eq!(Fungible::balance_of((1, 2)), 447);
+		assert_eq!(Balances::free_balance(&2), 9000);
+		assert_eq!(Fungible::balance_of((0, 2)), 0);
+		assert_eq!(Swaps::get_reserves(0), Some((3000, 1000)));
+
+		// Account 2 cannot pay in the rest of its currency and stay alive, nor
+		// tokens it does not hold.
+		assert_noop!(
+			Swaps::add_liquidity_single_sided(Origin::signed(2), 0, Asset::Currency, 9000, 1, 100),
+			Error::<Test>::InsufficientBalance,
+		);
+		assert_noop!(
+			Swaps::add_liquidity_single_sided(Origin::signed(2), 0, Asset::Token(0), 300, 1, 100),
+			Error::<Test>::InsufficientBalance,
+		);
+
+		// 141 tokens buy 369 currency, which leaves dust of both behind.
+		assert_ok!(Fungible::mint(0, 3, 300));
+		assert_ok!(Swaps::add_liquidity_single_sided(Origin::signed(3), 0, Asset::Token(0), 300, 1, 100));
+		assert_eq!(Fungible::balance_of((1, 3)), 340);
+		assert_eq!(Balances::free_balance(&3), 10003);
+		assert_eq!(Fungible::balance_of((0, 3)), 1);
+		assert_eq!(Swaps::get_reserves(0), Some((2997, 1299)));
+	});
+}
+
+#[test]
+fn removes_liquidity_to_a_single_asset() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+		assert_eq!(Balances::free_balance(&1), 8000);
+
+		// 500 shares are worth 500 currency and 250 tokens, which sell for 374
+		// currency once the shares are gone.
+		assert_noop!(
+			Swaps::remove_liquidity_one_side(Origin::signed(1), 0, 500, Side::Base, 875, 100),
+			Error::<Test>::NotEnoughCurrency,
+		);
+		assert_ok!(Swaps::remove_liquidity_one_side(Origin::signed(1), 0, 500, Side::Base, 874, 100));
+		assert_eq!(Balances::free_balance(&1), 8874);
+		assert_eq!(Fungible::balance_of((0, 1)), 0);
+		assert_eq!(Fungible::balance_of((1, 1)), 1500);
+		assert_eq!(Swaps::get_reserves(0), Some((1126, 1000)));
+
+		// Or 375 currency, sold for 221 tokens, and 333 tokens.
+		assert_noop!(
+			Swaps::remove_liquidity_one_side(Origin::signed(1), 0, 500, Side::Token, 555, 100),
+			Error::<Test>::NotEnoughTokens,
+		);
+		assert_ok!(Swaps::remove_liquidity_one_side(Origin::signed(1), 0, 500, Side::Token, 554, 100));
+		assert_eq!(Balances::free_balance(&1), 8874);
+		assert_eq!(Fungible::balance_of((0, 1)), 554);
+		assert_eq!(Swaps::get_reserves(0), Some((1126, 446)));
+	});
+}
+
+#[test]
+fn removing_liquidity_to_a_single_asset_checks_the_payout_first() {
+	new_test_ext().execute_with(|| {
+		set_existential_deposit(10);
+
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+		assert_ok!(Fungible::transfer(Origin::signed(1), 1, 8, 20));
+
+		// 4 shares are worth 4 currency and 2 tokens, which sell for 3, less
+		// than account 8 needs to exist.
+		assert_noop!(
+			Swaps::remove_liquidity_one_side(Origin::signed(8), 0, 4, Side::Base, 1, 100),
+			Error::<Test>::BelowExistentialDeposit,
+		);
+		assert_eq!(Fungible::balance_of((1, 8)), 20);
+		assert_eq!(Fungible::total_supply(1), 2000);
+		assert_eq!(Swaps::get_reserves(0), Some((2000, 1000)));
+		assert_eq!(Balances::free_balance(8), 0);
+
+		// 20 shares are worth 20 currency and 10 tokens, which sell for 19.
+		assert_ok!(Swaps::remove_liquidity_one_side(Origin::signed(8), 0, 20, Side::Base, 1, 100));
+		assert_eq!(Fungible::balance_of((1, 8)), 0);
+		assert_eq!(Balances::free_balance(8), 39);
+	});
+}
+
+#[test]
+fn farms_reward_staked_shares() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+		assert_ok!(Fungible::do_transfer(1, 1, 3, 500));
+		// Create TokenId 2 to pay rewards in.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(2), 10000));
+
+		assert_noop!(
+			Swaps::create_farm(Origin::signed(1), 0, 2, 10, 10, 20),
+			DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			Swaps::create_farm(Origin::ROOT, 0, 1, 10, 10, 20),
+			Error::<Test>::InvalidRewardToken,
+		);
+		assert_noop!(
+			Swaps::create_farm(Origin::ROOT, 0, 3, 10, 10, 20),
+			Error::<Test>::InvalidRewardToken,
+		);
+		assert_noop!(
+			Swaps::create_farm(Origin::ROOT, 0, 2, 10, 20, 10),
+			Error::<Test>::InvalidFarmSchedule,
+		);
+		// 10 tokens in every block from 11 to 20, only 60 of which are funded.
+		assert_ok!(Swaps::create_farm(Origin::ROOT, 0, 2, 10, 10, 20));
+		assert_noop!(
+			Swaps::create_farm(Origin::ROOT, 0, 2, 10, 10, 20),
+			Error::<Test>::FarmAlreadyExists,
+		);
+		assert_ok!(Swaps::fund_farm(Origin::signed(2), 0, 60));
+
+		System::set_block_number(5);
+		assert_ok!(Swaps::stake(Origin::signed(1), 0, 1000));
+		assert_eq!(Fungible::balance_of((1, Swaps::farm_account(0))), 1000);
+
+		// The first staker earns everything until the second joins.
+		System::set_block_number(15);
+		assert_eq!(Swaps::pending_rewards(0, &1), Ok(49));
+		assert_ok!(Swaps::stake(Origin::signed(3), 0, 500));
+
+		// After that the rewards are split by stake, until the farm ends.
+		System::set_block_number(25);
+		assert_eq!(Swaps::pending_rewards(0, &1), Ok(83));
+		assert_eq!(Swaps::pending_rewards(0, &3), Ok(16));
+
+		// Harvests are paid as far as the farm is funded.
+		assert_ok!(Swaps::harvest(Origin::signed(1), 0));
+		assert_eq!(Fungible::balance_of((2, 1)), 60);
+		assert_eq!(Swaps::pending_rewards(0, &1), Ok(23));
+		assert_noop!(Swaps::harvest(Origin::signed(3), 0), Error::<Test>::FarmUnderfunded);
+
+		assert_ok!(Swaps::fund_farm(Origin::signed(2), 0, 40));
+		assert_ok!(Swaps::harvest(Origin::signed(3), 0));
+		assert_ok!(Swaps::harvest(Origin::signed(1), 0));
+		assert_eq!(Fungible::balance_of((2, 3)), 16);
+		assert_eq!(Fungible::balance_of((2, 1)), 83);
+		assert_noop!(Swaps::harvest(Origin::signed(1), 0), Error::<Test>::NoRewardsOwed);
+
+		assert_noop!(Swaps::unstake(Origin::signed(1), 0, 1001), Error::<Test>::NotEnoughStaked);
+		assert_ok!(Swaps::unstake(Origin::signed(1), 0, 1000));
+		assert_eq!(Fungible::balance_of((1, 1)), 1500);
+		assert_eq!(Swaps::stakes((0, 1)), None);
+	});
+}
+
+#[test]
+fn keepers_execute_limit_orders() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+
+		// Sell 100 currency for at least 0.48 tokens each.
+		let limit_price = 48 * PRICE_PRECISION / 100;
+		assert_ok!(Swaps::place_limit_order(Origin::signed(2), 0, Side::Base, 100, limit_price, 100));
+		assert_eq!(Balances::free_balance(&2), 9900);
+		assert_eq!(Balances::free_balance(&Swaps::order_account(0)), 100);
+
+		// After the 1% tip, 99 currency only buys 47 tokens.
+		assert_noop!(Swaps::execute_order(Origin::signed(4), 0), Error::<Test>::LimitPriceNotReached);
+
+		// Until someone sells tokens into the swap.
+		assert_ok!(Fungible::mint(0, 3, 200));
+		assert_ok!(Swaps::tokens_to_currency_input(Origin::signed(3), 0, 200, 1, 100, 3));
+		assert_ok!(Swaps::execute_order(Origin::signed(4), 0));
+		assert_eq!(Fungible::balance_of((0, 2)), 67);
+		assert_eq!(Balances::free_balance(&4), 10001);
+		assert_eq!(Balances::free_balance(&Swaps::order_account(0)), 0);
+		assert_eq!(Swaps::get_reserves(0), Some((1767, 1133)));
+		assert_eq!(Swaps::orders(0), None);
+		assert_noop!(Swaps::execute_order(Origin::signed(4), 0), Error::<Test>::NoOrderExists);
+
+		// An order nobody fills can only be cancelled by its owner until it
+		// expires, and by anyone after.
+		assert_ok!(Swaps::place_limit_order(Origin::signed(2), 0, Side::Token, 50, 100 * PRICE_PRECISION, 10));
+		assert_eq!(Fungible::balance_of((0, 2)), 17);
+		assert_noop!(Swaps::cancel_order(Origin::signed(4), 1), Error::<Test>::NotOrderOwner);
+		System::set_block_number(11);
+		assert_noop!(Swaps::execute_order(Origin::signed(4), 1), Error::<Test>::OrderExpired);
+		assert_ok!(Swaps::cancel_order(Origin::signed(4), 1));
+		assert_eq!(Fungible::balance_of((0, 2)), 67);
+	});
+}
+
+#[test]
+fn limit_orders_are_checked_and_escrowed_separately() {
+	new_test_ext().execute_with(|| {
+		set_existential_deposit(10);
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+
+		// Orders need a limit price and a swap open to trading.
+		assert_noop!(
+			Swaps::place_limit_order(Origin::signed(2), 0, Side::Base, 100, 0, 100),
+			Error::<Test>::InvalidLimitPrice,
+		);
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, true));
+		assert_noop!(
+			Swaps::place_limit_order(Origin::signed(2), 0, Side::Base, 100, 1, 100),
+			Error::<Test>::SwapPaused,
+		);
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, false));
+
+		// Nor can they be placed on concentrated liquidity swaps. Create
+		// TokenId 2 and SwapId 1 for it against TokenId 0.
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_pair(Origin::signed(1), Asset::Token(0), Asset::Token(2)));
+		assert_ok!(Swaps::set_swap_concentrated(Origin::ROOT, 1, 10, U256::one() << 96));
+		assert_noop!(
+			Swaps::place_limit_order(Origin::signed(1), 1, Side::Base, 100, 1, 100),
+			Error::<Test>::ConcentratedSwap,
+		);
+
+		// Each order has an escrow account of its own, so filling one leaves
+		// the funds of the others alone.
+		assert_ok!(Swaps::place_limit_order(Origin::signed(2), 0, Side::Base, 100, 1, 100));
+		assert_ok!(Swaps::place_limit_order(Origin::signed(3), 0, Side::Base, 100, 1, 100));
+		assert_ok!(Swaps::execute_order(Origin::signed(4), 0));
+		assert_eq!(Fungible::balance_of((0, 2)), 47);
+		assert_eq!(Balances::free_balance(&4), 10001);
+		assert_eq!(Balances::free_balance(&Swaps::order_account(0)), 0);
+		assert_eq!(Balances::free_balance(&Swaps::order_account(1)), 100);
+
+		assert_ok!(Swaps::cancel_order(Origin::signed(3), 1));
+		assert_eq!(Balances::free_balance(&3), 10000);
+		assert_eq!(Balances::free_balance(&Swaps::order_account(1)), 0);
+	});
+}
+
+#[test]
+fn admin_pauses_trading_and_liquidity() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+
+		assert_noop!(Swaps::set_trading_paused(Origin::signed(1), true), DispatchError::BadOrigin);
+
+		// A trading pause still lets liquidity in.
+		assert_ok!(Swaps::set_trading_paused(Origin::ROOT, true));
+		assert_noop!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2),
+			Error::<Test>::TradingPaused
+		);
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 200, 1, 100, 100));
+		assert_ok!(Swaps::set_trading_paused(Origin::ROOT, false));
+
+		// And a liquidity pause still lets trades through.
+		assert_ok!(Swaps::set_liquidity_paused(Origin::ROOT, true));
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(1), 0, 200, 1, 100, 100),
+			Error::<Test>::LiquidityPaused
+		);
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2));
+		assert_ok!(Swaps::set_liquidity_paused(Origin::ROOT, false));
+
+		// A paused swap takes neither, but liquidity can always leave.
+		assert_noop!(Swaps::set_swap_paused(Origin::ROOT, 1, true), Error::<Test>::NoSwapExists);
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, true));
+		assert_noop!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2),
+			Error::<Test>::SwapPaused
+		);
+		assert_noop!(
+			Swaps::add_liquidity(Origin::signed(1), 0, 200, 1, 100, 100),
+			Error::<Test>::SwapPaused
+		);
+		assert_ok!(Swaps::remove_liquidity(Origin::signed(1), 0, 210, 1, 1, 100));
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, false));
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2));
+	});
+}
+
+#[test]
+fn circuit_breaker_pauses_swaps_on_large_price_moves() {
+	new_test_ext().execute_with(|| {
+		set_circuit_breaker(Permill::from_percent(10), 10);
+		assert_ok!(Fungible::debug_create_token(Origin::signed(1), 1000));
+		assert_ok!(Swaps::create_swap(Origin::signed(1), 0));
+		assert_ok!(Swaps::add_liquidity(Origin::signed(1), 0, 2000, 0, 1000, 100));
+
+		// The token price rises by 5%, and a trade that would take it 15%
+		// above where the window started trips the breaker. The trade fails
+		// without moving any funds and the swap is paused.
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 50, 1, 100, 2));
+		assert_eq!(Swaps::get_reserves(0), Some((2050, 976)));
+		assert_err!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2),
+			Error::<Test>::CircuitBreakerTripped
+		);
+		assert_eq!(Swaps::get_reserves(0), Some((2050, 976)));
+		assert_eq!(Balances::free_balance(&2), 9950);
+		assert!(Swaps::swap_paused(0));
+		assert_noop!(
+			Swaps::currency_to_tokens_input(Origin::signed(2), 0, 40, 1, 100, 2),
+			Error::<Test>::SwapPaused
+		);
+
+		// Unpausing starts a new window, which routes can trip as well.
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, false));
+		assert_err!(
+			Swaps::swap_exact_in_along_path(Origin::signed(2), vec![0], Asset::Currency, 150, 1, 100, 2),
+			Error::<Test>::CircuitBreakerTripped
+		);
+		assert_eq!(Swaps::get_reserves(0), Some((2050, 976)));
+		assert!(Swaps::swap_paused(0));
+		assert_ok!(Swaps::set_swap_paused(Origin::ROOT, 0, false));
+
+		// Smaller moves still go through.
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 40, 1, 100, 2));
+		assert_eq!(Swaps::get_reserves(0), Some((2090, 958)));
+		assert!(!Swaps::swap_paused(0));
+
+		// Moves are only measured within a window, so the same price can be
+		// reached over several.
+		System::set_block_number(20);
+		assert_ok!(Swaps::currency_to_tokens_input(Origin::signed(2), 0, 100, 1, 100, 2));
+		assert_eq!(Swaps::get_reserves(0), Some((2190, 915)));
+	});
+}
@@ -0,0 +1,140 @@
+//! Pricing for weighted pools, after Balancer's `BMath`.
+//!
+//! A weighted pool keeps `x^wx * y^wy` constant. Fractional powers are
+//! approximated with a binomial series in 18 decimal fixed point, which only
+//! converges for bases between 0 and 2, so callers must bound the size of a
+//! trade relative to the reserves. Amounts are rounded in favour of the pool
+//! and every function returns `None` if an intermediate value overflows.
+
+use sp_core::U256;
+
+/// One in the fixed point representation.
+const ONE: u128 = 1_000_000_000_000_000_000;
+
+/// The size of the last series term added to a fractional power.
+const POW_PRECISION: u128 = 100_000_000;
+
+/// The series terms added to a fractional power before giving up on
+/// convergence.
+const MAX_POW_TERMS: u128 = 100;
+
+/// The amount of `y` bought with `x_amount` of `x` from a pool holding `x`
+/// and `y` with weights `x_weight` and `y_weight`.
+pub fn output_amount(x: U256, x_weight: u32, y: U256, y_weight: u32, x_amount: U256) -> Option<U256> {
+	let one = U256::from(ONE);
+	let exponent = U256::from(x_weight).checked_mul(one)?.checked_div(U256::from(y_weight))?;
+	let ratio = div_up(x.checked_mul(one)?, x.checked_add(x_amount)?)?;
+	let remaining = one.checked_sub(pow(ratio, exponent)?)?;
+	y.checked_mul(remaining)?.checked_div(one)
+}
+
+/// The amount of `x` sold for `y_amount` of `y` to a pool holding `x` and `y`
+/// with weights `x_weight` and `y_weight`.
+pub fn input_amount(x: U256, x_weight: u32, y: U256, y_weight: u32, y_amount: U256) -> Option<U256> {
+	let one = U256::from(ONE);
+	let exponent = U256::from(y_weight).checked_mul(one)?.checked_div(U256::from(x_weight))?;
+	let ratio = div_up(y.checked_mul(one)?, y.checked_sub(y_amount)?)?;
+	let growth = pow(ratio, exponent)?.checked_sub(one)?;
+	div_up(x.checked_mul(growth)?, one)
+}
+
+/// Whether a pool that held `x` and `y` and now holds `new_x` and `new_y`
+/// has kept its invariant.
+pub fn invariant_holds(
+	x: U256,
+	x_weight: u32,
+	y: U256,
+	y_weight: u32,
+	new_x: U256,
+	new_y: U256,
+) -> Option<bool> {
+	// Whichever reserve grew must make up for the other falling, e.g.
+	// `new_y >= y * (x / new_x)^(x_weight / y_weight)`.
+	let required = |a: U256, a_weight: u32, b: U256, b_weight: u32, new_a: U256| -> Option<U256> {
+		let one = U256::from(ONE);
+		let exponent = U256::from(a_weight).checked_mul(one)?.checked_div(U256::from(b_weight))?;
+		let ratio = div_up(a.checked_mul(one)?, new_a)?;
+		div_up(b.checked_mul(pow(ratio, exponent)?)?, one)
+	};
+
+	if new_x >= x {
+		Some(new_y >= required(x, x_weight, y, y_weight, new_x)?)
+	} else if new_y >= y {
+		Some(new_x >= required(y, y_weight, x, x_weight, new_y)?)
+	} else {
+		Some(false)
+	}
+}
+
+/// `base^exponent` for a fixed point `base` between 0 and 2.
+fn pow(base: U256, exponent: U256) -> Option<U256> {
+	let one = U256::from(ONE);
+	let whole = exponent / one;
+	let remainder = exponent - whole * one;
+
+	let whole_pow = pow_whole(base, whole)?;
+	if remainder.is_zero() {
+		return Some(whole_pow);
+	}
+
+	whole_pow.checked_mul(pow_fraction(base, remainder)?)?.checked_div(one)
+}
+
+/// `base^exponent` by squaring, for a whole `exponent`.
+fn pow_whole(mut base: U256, mut exponent: U256) -> Option<U256> {
+	let one = U256::from(ONE);
+	let two = U256::from(2);
+	let mut result = if exponent % two == U256::one() { base } else { one };
+	exponent /= two;
+	while !exponent.is_zero() {
+		base = base.checked_mul(base)? / one;
+		if exponent % two == U256::one() {
+			result = result.checked_mul(base)? / one;
+		}
+		exponent /= two;
+	}
+	Some(result)
+}
+
+/// `base^exponent` for an `exponent` below one, as the binomial series of
+/// `(1 + (base - 1))^exponent`.
+fn pow_fraction(base: U256, exponent: U256) -> Option<U256> {
+	let one = U256::from(ONE);
+	let (x, x_negative) = abs_sub(base, one);
+	let mut term = one;
+	let mut sum = one;
+	let mut negative = false;
+
+	// Below one, every term after the first is negative, so cutting the
+	// series short leaves the result too high.
+	let mut k = 1;
+	while term >= U256::from(POW_PRECISION) && k <= MAX_POW_TERMS {
+		let big_k = U256::from(k).checked_mul(one)?;
+		let (c, c_negative) = abs_sub(exponent, big_k - one);
+		term = term.checked_mul(c.checked_mul(x)? / one)? / one;
+		term = term.checked_mul(one)? / big_k;
+		if term.is_zero() {
+			break;
+		}
+
+		if x_negative {
+			negative = !negative;
+		}
+		if c_negative {
+			negative = !negative;
+		}
+		sum = if negative { sum.checked_sub(term)? } else { sum.checked_add(term)? };
+		k += 1;
+	}
+	Some(sum)
+}
+
+/// `a - b` as a magnitude and whether it is negative.
+fn abs_sub(a: U256, b: U256) -> (U256, bool) {
+	if a >= b { (a - b, false) } else { (b - a, true) }
+}
+
+fn div_up(a: U256, b: U256) -> Option<U256> {
+	let quotient = a.checked_div(b)?;
+	if quotient * b == a { Some(quotient) } else { quotient.checked_add(U256::one()) }
+}
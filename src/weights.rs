@@ -0,0 +1,96 @@
+//! Weights for the Swaps pallet.
+
+use frame_support::weights::Weight;
+
+/// Weight functions needed by the Swaps pallet.
+pub trait WeightInfo {
+	fn create_swap() -> Weight;
+	fn set_swap_fee() -> Weight;
+	fn set_swap_curve() -> Weight;
+	fn ramp_amplification() -> Weight;
+	fn claim_protocol_fees() -> Weight;
+	fn add_liquidity() -> Weight;
+	/// Covers the bisection over quotes as well as the trade and deposit.
+	fn add_liquidity_single_sided() -> Weight;
+	fn remove_liquidity() -> Weight;
+	fn remove_liquidity_one_side() -> Weight;
+	fn currency_to_tokens_input() -> Weight;
+	fn currency_to_tokens_output() -> Weight;
+	fn tokens_to_currency_input() -> Weight;
+	fn tokens_to_currency_output() -> Weight;
+	fn swap_exact_in_along_path(p: u32) -> Weight;
+	fn swap_exact_out_along_path(p: u32) -> Weight;
+	/// Excludes the work done by the `FlashSwapHandler`, which declares its
+	/// own weight.
+	fn flash_swap() -> Weight;
+	fn mint_position() -> Weight;
+	fn burn_position() -> Weight;
+	fn collect_position_fees() -> Weight;
+	fn sync() -> Weight;
+	fn skim() -> Weight;
+	fn create_farm() -> Weight;
+	fn fund_farm() -> Weight;
+	fn stake() -> Weight;
+	fn unstake() -> Weight;
+	fn harvest() -> Weight;
+	fn place_limit_order() -> Weight;
+	fn execute_order() -> Weight;
+	fn cancel_order() -> Weight;
+	fn set_paused() -> Weight;
+}
+
+/// The weight of reading a storage item.
+const READ_WEIGHT: Weight = 100_000;
+/// The weight of writing a storage item.
+const WRITE_WEIGHT: Weight = 200_000;
+
+/// `execution` plus the weight of `reads` storage reads and `writes` storage
+/// writes.
+fn weight(execution: Weight, reads: Weight, writes: Weight) -> Weight {
+	execution
+		.saturating_add(READ_WEIGHT.saturating_mul(reads))
+		.saturating_add(WRITE_WEIGHT.saturating_mul(writes))
+}
+
+// Default weights laid out as the `runtime-benchmarks` suite reports them: the
+// execution time of the worst case of each call plus the distinct storage
+// items it reads and writes, balances included. Runtimes should replace them
+// with the results of the suite for their own configuration.
+impl WeightInfo for () {
+	fn create_swap() -> Weight { weight(400_000, 3, 7) }
+	fn set_swap_fee() -> Weight { weight(200_000, 1, 1) }
+	fn set_swap_curve() -> Weight { weight(250_000, 1, 1) }
+	fn ramp_amplification() -> Weight { weight(200_000, 1, 1) }
+	fn claim_protocol_fees() -> Weight { weight(500_000, 6, 5) }
+	fn add_liquidity() -> Weight { weight(800_000, 12, 8) }
+	// Includes the bisection over quotes for the sale.
+	fn add_liquidity_single_sided() -> Weight { weight(3_000_000, 14, 9) }
+	fn remove_liquidity() -> Weight { weight(700_000, 10, 8) }
+	fn remove_liquidity_one_side() -> Weight { weight(1_000_000, 11, 7) }
+	fn currency_to_tokens_input() -> Weight { weight(800_000, 11, 7) }
+	fn currency_to_tokens_output() -> Weight { weight(800_000, 11, 7) }
+	fn tokens_to_currency_input() -> Weight { weight(800_000, 11, 7) }
+	fn tokens_to_currency_output() -> Weight { weight(800_000, 11, 7) }
+	// Each swap in the path is read and written on top of the buyer's balance.
+	fn swap_exact_in_along_path(p: u32) -> Weight {
+		weight(200_000, 2, 1).saturating_add(weight(800_000, 9, 6).saturating_mul(p as Weight))
+	}
+	fn swap_exact_out_along_path(p: u32) -> Weight {
+		weight(200_000, 2, 1).saturating_add(weight(800_000, 9, 6).saturating_mul(p as Weight))
+	}
+	fn flash_swap() -> Weight { weight(1_200_000, 11, 8) }
+	fn mint_position() -> Weight { weight(1_200_000, 15, 13) }
+	fn burn_position() -> Weight { weight(1_200_000, 12, 12) }
+	fn collect_position_fees() -> Weight { weight(600_000, 10, 7) }
+	fn sync() -> Weight { weight(300_000, 4, 1) }
+	fn skim() -> Weight { weight(400_000, 6, 4) }
+	fn create_farm() -> Weight { weight(250_000, 3, 1) }
+	fn fund_farm() -> Weight { weight(300_000, 3, 2) }
+	fn stake() -> Weight { weight(500_000, 5, 4) }
+	fn unstake() -> Weight { weight(500_000, 5, 4) }
+	fn harvest() -> Weight { weight(500_000, 4, 4) }
+	fn place_limit_order() -> Weight { weight(400_000, 7, 4) }
+	fn execute_order() -> Weight { weight(1_000_000, 13, 9) }
+	fn cancel_order() -> Weight { weight(300_000, 4, 3) }
+	fn set_paused() -> Weight { weight(150_000, 1, 2) }
+}